@@ -0,0 +1,176 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+///Maps alternative logins, names or emails of a person to one canonical identity.
+///Loaded either from TOML (`canonical = ["alias", ...]`) or from a `.mailmap` file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AliasMap {
+    canonical: HashMap<String, String>,
+}
+
+impl AliasMap {
+    ///Reads `*.toml` files as TOML, anything else as `.mailmap`
+    ///# Errors
+    ///Fails when file can not be read or parsed.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read alias file {}", path.display()))?;
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            Self::from_toml(&content)
+        } else {
+            Self::from_mailmap(&content)
+        }
+        .with_context(|| format!("Could not parse alias file {}", path.display()))
+    }
+
+    ///```toml
+    ///luke = ["luke-work", "lskywalker"]
+    ///```
+    fn from_toml(content: &str) -> Result<Self> {
+        let identities: HashMap<String, Vec<String>> = toml::from_str(content)?;
+        let mut alias_map = AliasMap::default();
+        for (canonical, aliases) in identities {
+            for alias in aliases {
+                alias_map.insert(alias, &canonical);
+            }
+        }
+        Ok(alias_map)
+    }
+
+    ///Supports all `.mailmap` line forms:
+    ///`Proper Name <commit-key>`, `<proper-key> <commit-key>`,
+    ///`Proper Name <proper-key> <commit-key>` and `Proper Name <proper-key> Commit Name <commit-key>`.
+    ///Keys in angle brackets are matched against logins and emails.
+    fn from_mailmap(content: &str) -> Result<Self> {
+        let mut alias_map = AliasMap::default();
+        for (line_no, line) in content.lines().enumerate() {
+            let line = Self::strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let entries = Self::parse_mailmap_line(line)
+                .ok_or_else(|| anyhow!("Invalid .mailmap entry at line {}", line_no + 1))?;
+            match entries.as_slice() {
+                [(proper_name, proper_key)] => {
+                    let canonical = proper_name.unwrap_or(proper_key);
+                    alias_map.insert(proper_key.to_string(), canonical);
+                }
+                //Commit name only narrows the match in git, it is not an alias on its own
+                [(proper_name, proper_key), (_, commit_key)] => {
+                    let canonical = proper_name.unwrap_or(proper_key);
+                    alias_map.insert(proper_key.to_string(), canonical);
+                    alias_map.insert(commit_key.to_string(), canonical);
+                }
+                _ => return Err(anyhow!("Invalid .mailmap entry at line {}", line_no + 1)),
+            }
+        }
+        Ok(alias_map)
+    }
+
+    ///Drops a `#` comment starting the line or following whitespace outside of `<...>`,
+    ///so that emails such as `<dev#1@example.org>` are kept intact
+    fn strip_comment(line: &str) -> &str {
+        let mut in_key = false;
+        let mut previous = ' ';
+        for (index, character) in line.char_indices() {
+            match character {
+                '<' => in_key = true,
+                '>' => in_key = false,
+                '#' if !in_key && previous.is_whitespace() => {
+                    return &line[..index];
+                }
+                _ => {}
+            }
+            previous = character;
+        }
+        line
+    }
+
+    ///Splits line into `(optional name, key)` pairs
+    fn parse_mailmap_line(mut line: &str) -> Option<Vec<(Option<&str>, &str)>> {
+        let mut entries = vec![];
+        while !line.is_empty() {
+            let (name, rest) = line.split_once('<')?;
+            let (key, rest) = rest.split_once('>')?;
+            let name = Some(name.trim()).filter(|name| !name.is_empty());
+            entries.push((name, key.trim()));
+            line = rest.trim();
+        }
+        Some(entries)
+    }
+
+    fn insert(&mut self, alias: String, canonical: &str) {
+        if alias != canonical {
+            self.canonical.insert(alias, canonical.to_string());
+        }
+    }
+
+    ///Canonical identity of the given login, name or email, if it is an alias
+    pub fn canonical(&self, identity: &str) -> Option<&str> {
+        self.canonical.get(identity).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse_toml() {
+        let alias_map = AliasMap::from_toml(
+            r#"
+            luke = ["luke-work", "lskywalker"]
+            "Han Solo" = ["han"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(alias_map.canonical("luke-work"), Some("luke"));
+        assert_eq!(alias_map.canonical("lskywalker"), Some("luke"));
+        assert_eq!(alias_map.canonical("han"), Some("Han Solo"));
+        assert_eq!(alias_map.canonical("luke"), None);
+    }
+    #[test]
+    fn parse_mailmap() {
+        let alias_map = AliasMap::from_mailmap(
+            r#"
+            # comment
+            Luke Skywalker <luke>
+            Luke Skywalker <luke> <luke-work>
+            <han@rebels.org> <han@falcon.org>
+            Leia Organa <leia> Princess Leia <leia@alderaan.org> # trailing comment
+            "#,
+        )
+        .unwrap();
+        assert_eq!(alias_map.canonical("luke"), Some("Luke Skywalker"));
+        assert_eq!(alias_map.canonical("luke-work"), Some("Luke Skywalker"));
+        assert_eq!(
+            alias_map.canonical("han@falcon.org"),
+            Some("han@rebels.org")
+        );
+        assert_eq!(alias_map.canonical("han@rebels.org"), None);
+        assert_eq!(
+            alias_map.canonical("leia@alderaan.org"),
+            Some("Leia Organa")
+        );
+        assert_eq!(alias_map.canonical("Princess Leia"), None);
+    }
+    #[test]
+    fn parse_mailmap_hash_in_key() {
+        let alias_map = AliasMap::from_mailmap(
+            "Luke Skywalker <luke> <luke#work@rebels.org> # work account\nC#-dev <csharp> <c#@rebels.org>",
+        )
+        .unwrap();
+        assert_eq!(
+            alias_map.canonical("luke#work@rebels.org"),
+            Some("Luke Skywalker")
+        );
+        assert_eq!(alias_map.canonical("c#@rebels.org"), Some("C#-dev"));
+    }
+    #[test]
+    fn parse_mailmap_invalid() {
+        assert!(AliasMap::from_mailmap("Luke Skywalker luke").is_err());
+    }
+}
@@ -1,14 +1,38 @@
-use anyhow::Result;
-use bus_factor::BusFactorQueryCommand;
-use clap::Parser;
+use anyhow::{Context, Result};
+use bus_factor::{
+    parse_manifests, AliasMap, BotPolicy, BusFactorQueryCommand, ContributionWeight,
+    DependencyScan, DetectionStrategy, FlagCriterion, GitHubApi, OwnerKind, OwnerScan, Provider,
+    TimeWindow,
+};
+use clap::{CommandFactory, ErrorKind, Parser, Subcommand};
+use std::path::{Path, PathBuf};
 
 /// Simple program to fetch GitHub's projects which have bus factor
 #[derive(Parser, Debug)]
-#[clap(author, version, about, long_about = None)]
+#[clap(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
-    ///Name of the programming language
+    #[clap(subcommand)]
+    scan: Option<Scan>,
+    ///Comma separated names of the programming languages (can be repeated)
+    #[clap(
+        short,
+        long,
+        required_unless_present_any = ["repos", "repos-file", "topic"],
+        use_value_delimiter = true
+    )]
+    language: Vec<String>,
+    ///Comma separated owner/name repositories analysed instead of searching by language
+    #[clap(long, use_value_delimiter = true)]
+    repos: Vec<String>,
+    ///File with one owner/name repository per line, analysed instead of searching by language
+    #[clap(long)]
+    repos_file: Option<PathBuf>,
+    ///Comma separated topics searched projects have to be tagged with, usable instead of languages
+    #[clap(long, use_value_delimiter = true)]
+    topic: Vec<String>,
+    ///Additional GitHub search qualifiers, e.g. "stars:>1000 fork:false", or GitLab search terms (can be repeated)
     #[clap(short, long)]
-    language: String,
+    query: Vec<String>,
     ///Number of projects to consider
     #[clap(short, long, default_value = "10")]
     project_count: u32,
@@ -19,27 +43,222 @@ struct Args {
         default_value = "https://api.github.com/search/repositories"
     )]
     github_url: String,
+    ///Forge hosting the repositories
+    #[clap(long, value_enum, default_value = "github")]
+    provider: Provider,
+    ///GitHub API used with `--provider github`, GraphQL batches commit authors of many repositories per request
+    #[clap(long, value_enum, default_value = "rest")]
+    api: GitHubApi,
+    ///GitLab REST API root, used with `--provider gitlab`
+    #[clap(long, default_value = "https://gitlab.com/api/v4")]
+    gitlab_url: String,
+    ///Gitea or Forgejo REST API root, used with `--provider gitea`, e.g. https://git.example.com/api/v1
+    #[clap(long, default_value = "https://codeberg.org/api/v1")]
+    gitea_url: String,
+    ///Minimal percentage of contributions (1-100) owned by a contributor to report them as dominant
+    #[clap(short, long, default_value = "75", value_parser = clap::value_parser!(u32).range(1..=100))]
+    threshold: u32,
+    ///Share of all contributions (1-100), in the `--weight` unit, the smallest group of top contributors has to cover
+    #[clap(short, long, default_value = "50", value_parser = clap::value_parser!(u32).range(1..=100))]
+    coverage: u32,
+    ///Algorithm used to compute the bus factor
+    #[clap(short, long, value_enum, default_value = "commits")]
+    strategy: DetectionStrategy,
+    ///Maximal number of files inspected per project by the truck-factor strategy
+    #[clap(long, default_value = "1000", value_parser = clap::value_parser!(u32).range(1..))]
+    max_files: u32,
+    ///Maximal number of contributor pages (100 contributors each) fetched per project from a contributors endpoint (GitHub REST, GitLab)
+    #[clap(long, default_value = "10", value_parser = clap::value_parser!(u32).range(1..))]
+    max_contributor_pages: u32,
+    ///Maximal number of search pages crawled per search by the Gitea provider, which can not filter by language
+    #[clap(long, default_value = "20", value_parser = clap::value_parser!(u32).range(1..))]
+    max_search_pages: u32,
+    ///Maximal number of default branch commits counted per project by `--api graphql` and the Gitea provider,
+    ///which read the commit history instead of lifetime contributor counts
+    #[clap(long, default_value = "10000", value_parser = clap::value_parser!(u32).range(1..))]
+    max_commits: u32,
+    ///Count commits of authors without a linked GitHub account
+    #[clap(short, long)]
+    anonymous: bool,
+    ///How to treat automation accounts (`[bot]` suffix or `type: Bot`)
+    #[clap(short, long, value_enum, default_value = "drop")]
+    bots: BotPolicy,
+    ///Comma separated logins never treated as bots
+    #[clap(long, use_value_delimiter = true)]
+    allow_login: Vec<String>,
+    ///Comma separated logins always treated as bots
+    #[clap(long, use_value_delimiter = true)]
+    deny_login: Vec<String>,
+    ///Alias file merging identities of one person (`*.toml` or `.mailmap` format)
+    #[clap(long)]
+    aliases: Option<PathBuf>,
+    ///Only consider activity from this recent window, e.g. 90d, 8w, 12m, 2y
+    #[clap(long)]
+    since: Option<TimeWindow>,
+    ///Unit of contributions: commit count, added lines or added and deleted lines
+    #[clap(short, long, value_enum, default_value = "commits")]
+    weight: ContributionWeight,
+    ///Metric compared against the threshold to flag a project
+    #[clap(long, value_enum, default_value = "dominant")]
+    criterion: FlagCriterion,
+}
+
+///Scans repositories of a single owner instead of searching by language
+#[derive(Subcommand, Debug)]
+enum Scan {
+    ///Analyse every repository of a GitHub organization
+    Org(OwnerArgs),
+    ///Analyse every repository of a GitHub user
+    User(OwnerArgs),
+    ///Analyse repositories of dependencies listed in Cargo.lock or Cargo.toml
+    Deps(DependencyArgs),
+    ///Analyse local git clones with `git log`, without any API or access token
+    Local(LocalArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct OwnerArgs {
+    ///Login of the organization or user
+    name: String,
+    ///Also analyse forked repositories
+    #[clap(long)]
+    include_forks: bool,
+    ///Also analyse archived repositories
+    #[clap(long)]
+    include_archived: bool,
+    ///Skip repositories with fewer stargazers
+    #[clap(long, default_value = "0")]
+    min_stars: u32,
+}
+
+#[derive(clap::Args, Debug)]
+struct DependencyArgs {
+    ///Cargo.lock, Cargo.toml, package-lock.json, yarn.lock, poetry.lock, requirements.txt,
+    ///CycloneDX or SPDX JSON files
+    #[clap(required = true)]
+    manifests: Vec<PathBuf>,
+    ///crates.io compatible registry API used to find repositories of crates
+    #[clap(long, default_value = "https://crates.io/api/v1")]
+    registry_url: String,
+    ///npm registry used to find repositories of npm packages
+    #[clap(long, default_value = "https://registry.npmjs.org")]
+    npm_registry_url: String,
+    ///PyPI instance used to find repositories of Python packages
+    #[clap(long, default_value = "https://pypi.org")]
+    pypi_registry_url: String,
 }
 
-impl From<Args> for BusFactorQueryCommand {
-    fn from(cli_args: Args) -> Self {
-        BusFactorQueryCommand {
-            language: cli_args.language,
+#[derive(clap::Args, Debug)]
+struct LocalArgs {
+    ///Paths of local git repositories
+    #[clap(required = true)]
+    paths: Vec<PathBuf>,
+}
+
+impl OwnerArgs {
+    fn into_scan(self, kind: OwnerKind) -> OwnerScan {
+        OwnerScan {
+            kind,
+            name: self.name,
+            include_forks: self.include_forks,
+            include_archived: self.include_archived,
+            min_stars: self.min_stars,
+        }
+    }
+}
+
+impl TryFrom<Args> for BusFactorQueryCommand {
+    type Error = anyhow::Error;
+
+    fn try_from(cli_args: Args) -> Result<Self> {
+        let aliases = match &cli_args.aliases {
+            Some(path) => AliasMap::from_file(path)?,
+            None => AliasMap::default(),
+        };
+        let (owner, dependencies, local_repositories) = match cli_args.scan {
+            Some(Scan::Org(owner)) => {
+                (Some(owner.into_scan(OwnerKind::Organization)), None, vec![])
+            }
+            Some(Scan::User(owner)) => (Some(owner.into_scan(OwnerKind::User)), None, vec![]),
+            Some(Scan::Local(local)) => (None, None, local.paths),
+            Some(Scan::Deps(deps)) => (
+                None,
+                Some(DependencyScan {
+                    dependencies: parse_manifests(&deps.manifests)?,
+                    crates_registry_url: deps.registry_url.trim_end_matches('/').to_string(),
+                    npm_registry_url: deps.npm_registry_url.trim_end_matches('/').to_string(),
+                    pypi_registry_url: deps.pypi_registry_url.trim_end_matches('/').to_string(),
+                }),
+                vec![],
+            ),
+            None => (None, None, vec![]),
+        };
+        let mut repositories = cli_args.repos;
+        if let Some(path) = &cli_args.repos_file {
+            repositories.extend(read_repositories(path)?);
+        }
+        Ok(BusFactorQueryCommand {
+            languages: cli_args.language,
+            repositories,
+            owner,
+            dependencies,
+            local_repositories,
+            query: cli_args.query,
+            topics: cli_args.topic,
             project_count: cli_args.project_count,
+            provider: cli_args.provider,
+            api: cli_args.api,
             github_url: cli_args.github_url,
-        }
+            gitlab_url: cli_args.gitlab_url.trim_end_matches('/').to_string(),
+            gitea_url: cli_args.gitea_url.trim_end_matches('/').to_string(),
+            threshold: cli_args.threshold,
+            coverage: cli_args.coverage,
+            strategy: cli_args.strategy,
+            max_files: cli_args.max_files,
+            max_contributor_pages: cli_args.max_contributor_pages,
+            max_search_pages: cli_args.max_search_pages,
+            max_commits: cli_args.max_commits,
+            anonymous: cli_args.anonymous,
+            bot_policy: cli_args.bots,
+            allow_logins: cli_args.allow_login,
+            deny_logins: cli_args.deny_login,
+            aliases,
+            since: cli_args.since,
+            weight: cli_args.weight,
+            criterion: cli_args.criterion,
+        })
     }
 }
+///One `owner/name` per line, empty lines and `#` comments are skipped
+fn read_repositories(path: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read repositories file {}", path.display()))?;
+    Ok(content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
 fn main() -> Result<()> {
     env_logger::init();
     log::debug!("Starting Bus Factor");
     let args = Args::parse();
+    //Subcommands choose the analysed repositories themselves
+    if args.scan.is_some() && (!args.repos.is_empty() || args.repos_file.is_some()) {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--repos and --repos-file can not be used with a subcommand",
+            )
+            .exit();
+    }
 
-    let bus_factor_arguments: BusFactorQueryCommand = args.into();
+    let bus_factor_arguments: BusFactorQueryCommand = args.try_into()?;
     log::debug!(
         "Fetching data about top {} {} projects with highest stargazers number",
         &bus_factor_arguments.project_count,
-        &bus_factor_arguments.language
+        bus_factor_arguments.languages.join(", ")
     );
     bus_factor::initialize(bus_factor_arguments)
 }
@@ -0,0 +1,446 @@
+use crate::query_handler::bus_factor::{ContributorDetails, ContributorHandler};
+use crate::query_handler::gitea::GiteaHandler;
+use crate::query_handler::gitlab::GitLabHandler;
+use crate::query_handler::graphql::GraphQlHandler;
+use crate::query_handler::local_git::LocalGitHandler;
+use crate::query_handler::repository::RepositoryHandler;
+use crate::query_handler::truck_factor::TruckFactorHandler;
+use crate::query_handler::{HttpClientDetails, NoDataReason, RepositoryQueryResult};
+use crate::{BusFactorQueryCommand, ContributionWeight};
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use std::collections::HashMap;
+use std::env;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+
+const GITLAB_TOKEN_HEADER: &str = "private-token";
+
+///Forge repositories are discovered in and contributors are read from, plugged into the pipeline
+///with `initialize_with`. Explicit repository lists, dependency scans, bot filtering, aliases and
+///the detection itself are handled by the pipeline for every provider.
+pub trait ForgeProvider: Send + Sync + 'static {
+    ///Name used in error messages, e.g. `GitHub`
+    fn name(&self) -> &str;
+    ///Explicit repositories are `owner/name` unless the forge nests them deeper
+    fn is_repository_name(&self, repository: &str) -> bool {
+        let segments: Vec<&str> = repository.split('/').collect();
+        segments.len() == 2 && segments.iter().all(|segment| !segment.is_empty())
+    }
+    ///`discover` can list every repository of an organization or user
+    fn supports_owner_scan(&self) -> bool {
+        false
+    }
+    ///`repository` accepts the GitHub repositories package registries point to
+    fn supports_dependency_scan(&self) -> bool {
+        false
+    }
+    ///`contributors` honours the `since` window of the command
+    fn supports_time_window(&self) -> bool {
+        false
+    }
+    ///`file_histories` is implemented, required by the truck-factor strategy
+    fn supports_file_histories(&self) -> bool {
+        false
+    }
+    ///`contributors` measures added and deleted lines, required by line based weights
+    fn supports_line_weights(&self) -> bool {
+        false
+    }
+    ///Default headers of every API request, usually carrying the access token
+    fn authenticate(&self, command: &BusFactorQueryCommand) -> Result<HeaderMap>;
+    ///Repository named on the command line or resolved from a dependency, `None` when it does not exist
+    fn repository(
+        &self,
+        client_details: Arc<HttpClientDetails>,
+        repository: &str,
+    ) -> impl Future<Output = Result<Option<RepositoryQueryResult>>> + Send;
+    ///Sends every repository matching the languages, topics, query or owner of the command
+    fn discover(
+        &self,
+        client_details: Arc<HttpClientDetails>,
+        tx: Sender<RepositoryQueryResult>,
+    ) -> impl Future<Output = Result<()>> + Send;
+    ///Contributors of a repository, `contributions` measured in the requested weight.
+    ///`NoDataReason` errors are reported for the repository instead of failing the run.
+    fn contributors(
+        &self,
+        data: &RepositoryQueryResult,
+    ) -> impl Future<Output = Result<ContributorList>> + Send;
+    ///Commit authors of every file from the newest to the oldest commit, used by the truck-factor strategy
+    fn file_histories(
+        &self,
+        _data: &RepositoryQueryResult,
+    ) -> impl Future<Output = Result<HashMap<String, Vec<String>>>> + Send {
+        async { bail!(NoDataReason::Unsupported) }
+    }
+}
+
+///Contributors of one repository
+#[derive(Debug, Default)]
+pub struct ContributorList {
+    pub contributors: Vec<ContributorDetails>,
+    ///List was cut, e.g. at `max_contributor_pages`
+    pub truncated: bool,
+}
+
+///GitHub REST API, authenticated with `GITHUB_ACCESS_TOKEN`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GitHubProvider;
+
+impl ForgeProvider for GitHubProvider {
+    fn name(&self) -> &str {
+        "GitHub"
+    }
+    fn supports_owner_scan(&self) -> bool {
+        true
+    }
+    fn supports_dependency_scan(&self) -> bool {
+        true
+    }
+    fn supports_time_window(&self) -> bool {
+        true
+    }
+    fn supports_file_histories(&self) -> bool {
+        true
+    }
+    fn supports_line_weights(&self) -> bool {
+        true
+    }
+    fn authenticate(&self, _command: &BusFactorQueryCommand) -> Result<HeaderMap> {
+        token_header("GITHUB_ACCESS_TOKEN", AUTHORIZATION, "token ")
+    }
+    async fn repository(
+        &self,
+        client_details: Arc<HttpClientDetails>,
+        repository: &str,
+    ) -> Result<Option<RepositoryQueryResult>> {
+        RepositoryHandler::fetch_repository(repository, client_details).await
+    }
+    async fn discover(
+        &self,
+        client_details: Arc<HttpClientDetails>,
+        tx: Sender<RepositoryQueryResult>,
+    ) -> Result<()> {
+        RepositoryHandler::discover(client_details, tx).await
+    }
+    ///Contributors endpoint for plain commit counts, weekly statistics for line weights and windows
+    async fn contributors(&self, data: &RepositoryQueryResult) -> Result<ContributorList> {
+        let command = data.client_details.command();
+        let (contributors, truncated) = match (command.weight, &command.since) {
+            (ContributionWeight::Commits, None) => {
+                ContributorHandler::fetch_all_pages(data).await?
+            }
+            (weight, window) => (
+                ContributorHandler::fetch_activity(data, weight, window.as_ref()).await?,
+                false,
+            ),
+        };
+        Ok(ContributorList {
+            contributors,
+            truncated,
+        })
+    }
+    async fn file_histories(
+        &self,
+        data: &RepositoryQueryResult,
+    ) -> Result<HashMap<String, Vec<String>>> {
+        TruckFactorHandler::fetch_file_histories(data).await
+    }
+}
+
+///GitHub GraphQL API v4, authenticated with `GITHUB_ACCESS_TOKEN`.
+///Commit authors are counted from the default branch history, whose first page is fetched
+///together with the repositories; the truck-factor strategy reads file histories via REST.
+#[derive(Debug, Default)]
+pub struct GitHubGraphQlProvider {
+    handler: GraphQlHandler,
+}
+
+impl ForgeProvider for GitHubGraphQlProvider {
+    fn name(&self) -> &str {
+        "GitHub"
+    }
+    fn supports_owner_scan(&self) -> bool {
+        true
+    }
+    fn supports_dependency_scan(&self) -> bool {
+        true
+    }
+    fn supports_time_window(&self) -> bool {
+        true
+    }
+    fn supports_file_histories(&self) -> bool {
+        true
+    }
+    fn supports_line_weights(&self) -> bool {
+        true
+    }
+    fn authenticate(&self, command: &BusFactorQueryCommand) -> Result<HeaderMap> {
+        GitHubProvider.authenticate(command)
+    }
+    async fn repository(
+        &self,
+        client_details: Arc<HttpClientDetails>,
+        repository: &str,
+    ) -> Result<Option<RepositoryQueryResult>> {
+        self.handler
+            .fetch_repository(repository, client_details)
+            .await
+    }
+    async fn discover(
+        &self,
+        client_details: Arc<HttpClientDetails>,
+        tx: Sender<RepositoryQueryResult>,
+    ) -> Result<()> {
+        match &client_details.command().owner {
+            Some(owner) => {
+                self.handler
+                    .fetch_owner_repositories(owner, client_details.clone(), tx)
+                    .await
+            }
+            None => self.handler.search_repositories(client_details, tx).await,
+        }
+    }
+    async fn contributors(&self, data: &RepositoryQueryResult) -> Result<ContributorList> {
+        let (contributors, truncated) = self.handler.fetch_contributors(data).await?;
+        Ok(ContributorList {
+            contributors,
+            truncated,
+        })
+    }
+    async fn file_histories(
+        &self,
+        data: &RepositoryQueryResult,
+    ) -> Result<HashMap<String, Vec<String>>> {
+        GitHubProvider.file_histories(data).await
+    }
+}
+
+///GitLab REST API v4, authenticated with `GITLAB_ACCESS_TOKEN`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GitLabProvider;
+
+impl ForgeProvider for GitLabProvider {
+    fn name(&self) -> &str {
+        "GitLab"
+    }
+    ///Projects may live in subgroups, e.g. `group/subgroup/name`
+    fn is_repository_name(&self, repository: &str) -> bool {
+        let segments: Vec<&str> = repository.split('/').collect();
+        segments.len() >= 2 && segments.iter().all(|segment| !segment.is_empty())
+    }
+    fn authenticate(&self, _command: &BusFactorQueryCommand) -> Result<HeaderMap> {
+        token_header(
+            "GITLAB_ACCESS_TOKEN",
+            HeaderName::from_static(GITLAB_TOKEN_HEADER),
+            "",
+        )
+    }
+    async fn repository(
+        &self,
+        client_details: Arc<HttpClientDetails>,
+        repository: &str,
+    ) -> Result<Option<RepositoryQueryResult>> {
+        GitLabHandler::fetch_project(repository, client_details).await
+    }
+    async fn discover(
+        &self,
+        client_details: Arc<HttpClientDetails>,
+        tx: Sender<RepositoryQueryResult>,
+    ) -> Result<()> {
+        GitLabHandler::search_projects(client_details, tx).await
+    }
+    ///GitLab contributors are identified by name and email only
+    async fn contributors(&self, data: &RepositoryQueryResult) -> Result<ContributorList> {
+        let weight = data.client_details.command().weight;
+        let (contributors, truncated) = GitLabHandler::fetch_contributors(data).await?;
+        Ok(ContributorList {
+            contributors: contributors
+                .into_iter()
+                .map(|contributor| ContributorDetails {
+                    contributions: weight.measure(
+                        contributor.commits,
+                        contributor.additions,
+                        contributor.deletions,
+                    ),
+                    login: None,
+                    name: Some(contributor.name),
+                    email: Some(contributor.email),
+                    kind: String::new(),
+                    other: Default::default(),
+                })
+                .collect(),
+            truncated,
+        })
+    }
+}
+
+///Gitea or Forgejo REST API v1, authenticated with `GITEA_ACCESS_TOKEN`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GiteaProvider;
+
+impl ForgeProvider for GiteaProvider {
+    fn name(&self) -> &str {
+        "Gitea"
+    }
+    fn supports_line_weights(&self) -> bool {
+        true
+    }
+    fn authenticate(&self, _command: &BusFactorQueryCommand) -> Result<HeaderMap> {
+        token_header("GITEA_ACCESS_TOKEN", AUTHORIZATION, "token ")
+    }
+    async fn repository(
+        &self,
+        client_details: Arc<HttpClientDetails>,
+        repository: &str,
+    ) -> Result<Option<RepositoryQueryResult>> {
+        GiteaHandler::fetch_repository(repository, client_details).await
+    }
+    async fn discover(
+        &self,
+        client_details: Arc<HttpClientDetails>,
+        tx: Sender<RepositoryQueryResult>,
+    ) -> Result<()> {
+        GiteaHandler::search_repositories(client_details, tx).await
+    }
+    ///Commit authors, identified by login when their commits are linked to an account
+    async fn contributors(&self, data: &RepositoryQueryResult) -> Result<ContributorList> {
+        let weight = data.client_details.command().weight;
+        let (authors, truncated) = GiteaHandler::count_authors(data).await?;
+        Ok(ContributorList {
+            contributors: authors
+                .into_iter()
+                .map(|author| ContributorDetails {
+                    contributions: weight.measure(
+                        author.commits,
+                        author.additions,
+                        author.deletions,
+                    ),
+                    login: author.login,
+                    name: Some(author.name),
+                    email: Some(author.email),
+                    kind: String::new(),
+                    other: Default::default(),
+                })
+                .collect(),
+            truncated,
+        })
+    }
+}
+
+///Local clones read with `git log`, no API and no access token involved.
+///Repositories are paths, `repository_url` of discovered repositories holds the canonical path.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LocalGitProvider;
+
+impl ForgeProvider for LocalGitProvider {
+    fn name(&self) -> &str {
+        "local git"
+    }
+    fn supports_time_window(&self) -> bool {
+        true
+    }
+    fn supports_file_histories(&self) -> bool {
+        true
+    }
+    fn supports_line_weights(&self) -> bool {
+        true
+    }
+    fn authenticate(&self, _command: &BusFactorQueryCommand) -> Result<HeaderMap> {
+        Ok(HeaderMap::new())
+    }
+    ///Local clones need no lookup, they are named after their directory
+    async fn repository(
+        &self,
+        client_details: Arc<HttpClientDetails>,
+        repository: &str,
+    ) -> Result<Option<RepositoryQueryResult>> {
+        let path = Path::new(repository)
+            .canonicalize()
+            .with_context(|| format!("Could not open repository {repository}"))?;
+        let project_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+        Ok(Some(RepositoryQueryResult {
+            stargazers: 0,
+            contributor_url: String::new(),
+            repository_url: path.display().to_string(),
+            default_branch: "HEAD".to_string(),
+            project_name,
+            language: String::new(),
+            dependencies: vec![],
+            client_details,
+        }))
+    }
+    async fn discover(
+        &self,
+        client_details: Arc<HttpClientDetails>,
+        tx: Sender<RepositoryQueryResult>,
+    ) -> Result<()> {
+        for path in client_details.command().local_repositories.iter().unique() {
+            let repository = self
+                .repository(client_details.clone(), &path.display().to_string())
+                .await?;
+            if let Some(repository) = repository {
+                tx.send(repository)
+                    .await
+                    .expect("Could not send message to contributor query handler");
+            }
+        }
+        Ok(())
+    }
+    ///Authors identified by git author name and email
+    async fn contributors(&self, data: &RepositoryQueryResult) -> Result<ContributorList> {
+        let command = data.client_details.command();
+        let authors =
+            LocalGitHandler::read_activity(Path::new(&data.repository_url), command.since.as_ref())
+                .await?;
+        Ok(ContributorList {
+            contributors: authors
+                .into_iter()
+                .map(|author| ContributorDetails {
+                    contributions: command.weight.measure(
+                        author.commits,
+                        author.additions,
+                        author.deletions,
+                    ),
+                    login: None,
+                    name: Some(author.name),
+                    email: Some(author.email),
+                    kind: String::new(),
+                    other: Default::default(),
+                })
+                .collect(),
+            truncated: false,
+        })
+    }
+    async fn file_histories(
+        &self,
+        data: &RepositoryQueryResult,
+    ) -> Result<HashMap<String, Vec<String>>> {
+        let command = data.client_details.command();
+        LocalGitHandler::read_file_histories(
+            Path::new(&data.repository_url),
+            command.max_files as usize,
+            &command.aliases,
+            command.since.as_ref(),
+        )
+        .await
+    }
+}
+
+///`header: {prefix}{token}` with the access token read from the `variable` environment variable
+fn token_header(variable: &str, header: HeaderName, prefix: &str) -> Result<HeaderMap> {
+    let token = match env::var(variable) {
+        Ok(token) => token,
+        Err(err) => bail!("Missing {variable} environment variable. {err}"),
+    };
+    let mut headers = HeaderMap::new();
+    headers.append(header, HeaderValue::from_str(&format!("{prefix}{token}"))?);
+    Ok(headers)
+}
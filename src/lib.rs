@@ -1,37 +1,453 @@
-use anyhow::Result;
-use query_handler::{bus_factor, repository};
+use anyhow::{bail, Result};
+use itertools::Itertools;
+use query_handler::{bus_factor, registry, repository};
+use std::path::PathBuf;
+use std::sync::Arc;
 
+mod alias_map;
+mod forge;
+mod manifest;
 mod query_handler;
 mod report_generator;
 
+pub use alias_map::AliasMap;
+pub use forge::{
+    ContributorList, ForgeProvider, GitHubGraphQlProvider, GitHubProvider, GitLabProvider,
+    GiteaProvider, LocalGitProvider,
+};
+pub use manifest::{parse_manifest, parse_manifests, Dependency, DependencyKind, Ecosystem};
+pub use query_handler::bus_factor::ContributorDetails;
+pub use query_handler::contributor_stats::TimeWindow;
+pub use query_handler::{
+    BusFactorQueryResult, ConcentrationMetrics, DominantContributor, HttpClientDetails,
+    NoDataReason, RepositoryQueryResult,
+};
+
 #[derive(Clone, Debug)]
 pub struct BusFactorQueryCommand {
-    pub language: String,
+    ///Languages searched one by one, each contributing its own top `project_count` projects
+    pub languages: Vec<String>,
+    ///Explicit `owner/name` repositories analysed instead of searching by language
+    pub repositories: Vec<String>,
+    ///Every repository of an organization or user analysed instead of searching by language
+    pub owner: Option<OwnerScan>,
+    ///GitHub repositories of dependencies analysed instead of searching by language
+    pub dependencies: Option<DependencyScan>,
+    ///Local clones analysed with `git log` instead of any API, no access token needed
+    pub local_repositories: Vec<PathBuf>,
+    ///Additional GitHub search qualifiers, e.g. `stars:>1000` or `org:rust-lang`
+    pub query: Vec<String>,
+    ///Topics searched projects have to be tagged with, usable instead of languages
+    pub topics: Vec<String>,
     pub project_count: u32,
+    ///Forge hosting the analysed repositories
+    pub provider: Provider,
+    ///GitHub API flavour, only used with the GitHub provider
+    pub api: GitHubApi,
     pub github_url: String,
+    ///GitLab REST API root, e.g. `https://gitlab.com/api/v4`
+    pub gitlab_url: String,
+    ///Gitea or Forgejo REST API root, e.g. `https://codeberg.org/api/v1`
+    pub gitea_url: String,
+    pub threshold: u32,
+    pub coverage: u32,
+    pub strategy: DetectionStrategy,
+    pub max_files: u32,
+    pub max_contributor_pages: u32,
+    ///Pages crawled per search on forges which can not filter by language (Gitea)
+    pub max_search_pages: u32,
+    ///Default branch commits counted per project by providers reading the commit history
+    ///instead of a contributors endpoint (GitHub GraphQL, Gitea)
+    pub max_commits: u32,
+    pub anonymous: bool,
+    pub bot_policy: BotPolicy,
+    ///Logins never treated as bots
+    pub allow_logins: Vec<String>,
+    ///Logins always treated as bots
+    pub deny_logins: Vec<String>,
+    ///Alternative identities folded into one person before calculation
+    pub aliases: AliasMap,
+    ///Only activity within this recent window is considered (uses contributor statistics)
+    pub since: Option<TimeWindow>,
+    pub weight: ContributionWeight,
+    pub criterion: FlagCriterion,
+}
+
+impl BusFactorQueryCommand {
+    ///Distinct trimmed languages to search one by one, a single empty language for topic-only searches
+    pub fn search_languages(&self) -> Vec<String> {
+        let languages: Vec<String> = self
+            .languages
+            .iter()
+            .map(|language| language.trim().to_string())
+            .filter(|language| !language.is_empty())
+            .unique()
+            .collect();
+        if languages.is_empty() {
+            return vec![String::new()];
+        }
+        languages
+    }
+}
+
+///Built-in forge API repositories are discovered in and contributors are read from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Provider {
+    ///GitHub REST API, authenticated with `GITHUB_ACCESS_TOKEN`
+    #[clap(name = "github")]
+    GitHub,
+    ///GitLab REST API v4, authenticated with `GITLAB_ACCESS_TOKEN`
+    #[clap(name = "gitlab")]
+    GitLab,
+    ///Gitea or Forgejo REST API v1, authenticated with `GITEA_ACCESS_TOKEN`
+    #[clap(name = "gitea", alias = "forgejo")]
+    Gitea,
+}
+
+impl std::fmt::Display for Provider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Provider::GitHub => write!(f, "GitHub"),
+            Provider::GitLab => write!(f, "GitLab"),
+            Provider::Gitea => write!(f, "Gitea"),
+        }
+    }
+}
+
+///GitHub API repositories and contributors are read through
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum GitHubApi {
+    ///REST API v3, one request per search page and per contributor page
+    Rest,
+    ///GraphQL API v4, commit authors of many repositories fetched in one batched query
+    #[clap(name = "graphql")]
+    GraphQl,
+}
+
+///Repositories listed by `/orgs/{name}/repos` or `/users/{name}/repos`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OwnerScan {
+    pub kind: OwnerKind,
+    pub name: String,
+    pub include_forks: bool,
+    pub include_archived: bool,
+    pub min_stars: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OwnerKind {
+    Organization,
+    User,
+}
+
+///Dependencies read from manifests, resolved to repositories via registry APIs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DependencyScan {
+    pub dependencies: Vec<Dependency>,
+    ///crates.io compatible registry API, e.g. `https://crates.io/api/v1`
+    pub crates_registry_url: String,
+    ///npm registry, e.g. `https://registry.npmjs.org`
+    pub npm_registry_url: String,
+    ///PyPI JSON API root, e.g. `https://pypi.org`
+    pub pypi_registry_url: String,
+}
+
+///What to do with automation accounts found among contributors
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum BotPolicy {
+    ///Remove bots before any calculation
+    Drop,
+    ///Count bot commits, but never report a bot as the dominant contributor
+    ExcludeFromDominant,
+    ///Treat bots as regular contributors
+    Keep,
+}
+
+///Unit in which work of a contributor is measured
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ContributionWeight {
+    ///Number of commits
+    Commits,
+    ///Number of added lines (uses contributor statistics)
+    Additions,
+    ///Number of added and deleted lines (uses contributor statistics)
+    Churn,
+}
+
+impl ContributionWeight {
+    ///Picks the value measured by this weight
+    pub fn measure(self, commits: u64, additions: u64, deletions: u64) -> u64 {
+        match self {
+            ContributionWeight::Commits => commits,
+            ContributionWeight::Additions => additions,
+            ContributionWeight::Churn => additions.saturating_add(deletions),
+        }
+    }
+}
+
+impl std::fmt::Display for ContributionWeight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContributionWeight::Commits => write!(f, "commits"),
+            ContributionWeight::Additions => write!(f, "additions"),
+            ContributionWeight::Churn => write!(f, "churn"),
+        }
+    }
+}
+
+///Metric compared against `threshold` to flag a project
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum FlagCriterion {
+    ///Any contributor owns at least `threshold` percent
+    Dominant,
+    ///Gini coefficient (in percent) reaches `threshold`
+    Gini,
+    ///Herfindahl-Hirschman index (in percent) reaches `threshold`
+    Hhi,
+    ///Inverted normalized Shannon entropy (in percent) reaches `threshold`
+    Entropy,
+    ///Share of top three contributors reaches `threshold` percent
+    Top3,
+}
+
+impl std::fmt::Display for FlagCriterion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlagCriterion::Dominant => write!(f, "dominant contributor share"),
+            FlagCriterion::Gini => write!(f, "gini"),
+            FlagCriterion::Hhi => write!(f, "hhi"),
+            FlagCriterion::Entropy => write!(f, "inverted normalized entropy"),
+            FlagCriterion::Top3 => write!(f, "top-3 share"),
+        }
+    }
+}
+
+///How the bus factor of a single project is computed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum DetectionStrategy {
+    ///Commit share of each contributor as reported by the contributors endpoint
+    Commits,
+    ///Degree-of-authorship truck factor based on per-file commit history
+    TruckFactor,
+}
+
+impl Default for BusFactorQueryCommand {
+    fn default() -> Self {
+        BusFactorQueryCommand {
+            languages: vec![],
+            repositories: vec![],
+            owner: None,
+            dependencies: None,
+            local_repositories: vec![],
+            query: vec![],
+            topics: vec![],
+            project_count: 10,
+            provider: Provider::GitHub,
+            api: GitHubApi::Rest,
+            github_url: "https://api.github.com/search/repositories".to_string(),
+            gitlab_url: "https://gitlab.com/api/v4".to_string(),
+            gitea_url: "https://codeberg.org/api/v1".to_string(),
+            threshold: 75,
+            coverage: 50,
+            strategy: DetectionStrategy::Commits,
+            max_files: 1000,
+            max_contributor_pages: 10,
+            max_commits: 10000,
+            max_search_pages: 20,
+            anonymous: false,
+            bot_policy: BotPolicy::Drop,
+            allow_logins: vec![],
+            deny_logins: vec![],
+            aliases: AliasMap::default(),
+            since: None,
+            weight: ContributionWeight::Commits,
+            criterion: FlagCriterion::Dominant,
+        }
+    }
 }
-///Utilize Async Pipeline Design Pattern
+///Utilize Async Pipeline Design Pattern, reading local clones or the forge selected by `provider`
 ///# Errors
 ///May fail when can not create Runtime/Channels.
 pub fn initialize(command: BusFactorQueryCommand) -> Result<()> {
-    validatate_command(&command);
+    if command.api == GitHubApi::GraphQl
+        && (command.provider != Provider::GitHub || !command.local_repositories.is_empty())
+    {
+        bail!("GraphQL API is only available with the GitHub provider");
+    }
+    if command.provider == Provider::Gitea
+        && command.topics.iter().any(|topic| !topic.trim().is_empty())
+        && !command.query.is_empty()
+    {
+        bail!("Gitea search matches either topics or query terms, --topic can not be combined with --query");
+    }
+    if !command.local_repositories.is_empty() {
+        return initialize_with(command, LocalGitProvider);
+    }
+    match command.provider {
+        Provider::GitHub if command.api == GitHubApi::GraphQl => {
+            initialize_with(command, GitHubGraphQlProvider::default())
+        }
+        Provider::GitHub => initialize_with(command, GitHubProvider),
+        Provider::GitLab => initialize_with(command, GitLabProvider),
+        Provider::Gitea => initialize_with(command, GiteaProvider),
+    }
+}
+///Runs the pipeline against any forge instead of the built-in one selected by `command.provider`
+///and prints the report
+///# Errors
+///May fail when can not create Runtime/Channels, when the command asks for something the provider
+///does not support, or when a forge request fails for other reasons than missing repository data.
+pub fn initialize_with<P: ForgeProvider>(
+    command: BusFactorQueryCommand,
+    provider: P,
+) -> Result<()> {
+    let Some((results, unresolved)) = analyse(&command, provider)? else {
+        log::debug!("Nothing to analyse");
+        return Ok(());
+    };
+    report_generator::ReportGenerator::print(&results, &command, &unresolved);
+    log::debug!("Finalising");
+    Ok(())
+}
+///Runs the pipeline like [`initialize_with`] but returns results of the analysed projects
+///instead of printing them
+///# Errors
+///Fails in the same cases as [`initialize_with`].
+pub fn analyse_with<P: ForgeProvider>(
+    command: BusFactorQueryCommand,
+    provider: P,
+) -> Result<Vec<BusFactorQueryResult>> {
+    Ok(analyse(&command, provider)?
+        .map(|(results, _)| results)
+        .unwrap_or_default())
+}
+///Results with dependencies whose repository is unknown, `None` when there is nothing to analyse
+fn analyse<P: ForgeProvider>(
+    command: &BusFactorQueryCommand,
+    provider: P,
+) -> Result<
+    Option<(
+        Vec<BusFactorQueryResult>,
+        Vec<registry::UnresolvedDependency>,
+    )>,
+> {
+    if !validatate_command(command, &provider)? {
+        return Ok(None);
+    }
+    let command = command.clone();
+    let provider = Arc::new(provider);
     let runtime = tokio::runtime::Runtime::new()?;
     runtime.block_on(async move {
-        report_generator::ReportGenerator::run(
-            bus_factor::ContributorHandler::run(repository::RepositoryHandler::run(command).await?)
-                .await?,
+        let resolution = match &command.dependencies {
+            Some(scan) => registry::RegistryHandler::resolve(scan).await?,
+            None => registry::Resolution::default(),
+        };
+        let (tx, results) = tokio::sync::mpsc::channel(1000);
+        let (repositories, repository_stage) = repository::RepositoryHandler::run(
+            command,
+            provider.clone(),
+            resolution.repositories,
+            tx.clone(),
         )
-        .await
-    })?;
-    log::debug!("Finalising");
-    Ok(())
+        .await?;
+        let contributor_stage = bus_factor::ContributorHandler::run(repositories, provider, tx);
+        let results = report_generator::ReportGenerator::collect(
+            results,
+            vec![contributor_stage, repository_stage],
+        )
+        .await?;
+        Ok(Some((results, resolution.unresolved)))
+    })
 }
-//Immediate results
-fn validatate_command(command: &BusFactorQueryCommand) {
-    if command.project_count == 0 {
-        std::process::exit(0);
+//Immediate results, `false` when there is nothing to analyse
+fn validatate_command<P: ForgeProvider>(
+    command: &BusFactorQueryCommand,
+    provider: &P,
+) -> Result<bool> {
+    if !(1..=100).contains(&command.threshold) {
+        bail!(
+            "Threshold must be between 1 and 100, got {}",
+            command.threshold
+        );
+    }
+    if !(1..=100).contains(&command.coverage) {
+        bail!(
+            "Coverage must be between 1 and 100, got {}",
+            command.coverage
+        );
+    }
+    for (limit, value) in [
+        ("max_files", command.max_files),
+        ("max_contributor_pages", command.max_contributor_pages),
+        ("max_search_pages", command.max_search_pages),
+        ("max_commits", command.max_commits),
+    ] {
+        if value == 0 {
+            bail!("{limit} must be at least 1");
+        }
+    }
+    if !command.local_repositories.is_empty()
+        && (!command.repositories.is_empty()
+            || command.owner.is_some()
+            || command.dependencies.is_some())
+    {
+        bail!("Local repositories can not be combined with forge repositories, owner or dependency scans");
+    }
+    if let Some(repository) = command
+        .repositories
+        .iter()
+        .find(|repository| !provider.is_repository_name(repository))
+    {
+        bail!("Repository must be given as owner/name, got {repository}");
+    }
+    if command.owner.is_some() && !provider.supports_owner_scan() {
+        bail!(
+            "{} provider does not support organization or user scans",
+            provider.name()
+        );
+    }
+    if command.dependencies.is_some() && !provider.supports_dependency_scan() {
+        bail!(
+            "{} provider does not support dependency scans",
+            provider.name()
+        );
+    }
+    if command.strategy == DetectionStrategy::TruckFactor && !provider.supports_file_histories() {
+        bail!(
+            "{} provider does not support the truck-factor strategy",
+            provider.name()
+        );
+    }
+    //Truck factor counts authored files, so a line based weight would be silently ignored
+    if command.strategy == DetectionStrategy::TruckFactor
+        && command.weight != ContributionWeight::Commits
+    {
+        bail!(
+            "--weight {} can not be combined with the truck-factor strategy",
+            command.weight
+        );
+    }
+    if command.weight != ContributionWeight::Commits && !provider.supports_line_weights() {
+        bail!(
+            "{} provider does not support --weight {}",
+            provider.name(),
+            command.weight
+        );
+    }
+    if command.since.is_some() && !provider.supports_time_window() {
+        bail!("{} provider does not support --since", provider.name());
     }
-    if command.language.is_empty() {
-        std::process::exit(0);
+    if !command.repositories.is_empty()
+        || command.owner.is_some()
+        || command.dependencies.is_some()
+        || !command.local_repositories.is_empty()
+    {
+        return Ok(true);
     }
+    Ok(command.project_count > 0
+        && command
+            .languages
+            .iter()
+            .chain(&command.topics)
+            .any(|filter| !filter.trim().is_empty()))
 }
@@ -0,0 +1,208 @@
+use crate::manifest::{Dependency, DependencyKind, Ecosystem, ManifestParser};
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+#[derive(Deserialize, Debug)]
+struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+#[derive(Deserialize, Debug)]
+struct LockedPackage {
+    name: String,
+    ///Missing for workspace members and path dependencies
+    source: Option<String>,
+    ///`name`, `name version` or `name version (source)`
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+pub(crate) struct CargoLock;
+pub(crate) struct CargoManifest;
+
+impl ManifestParser for CargoLock {
+    fn accepts(&self, file_name: &str) -> bool {
+        file_name == "Cargo.lock"
+    }
+    fn parse(&self, content: &str) -> Result<Vec<Dependency>> {
+        parse_lock(content)
+    }
+}
+
+impl ManifestParser for CargoManifest {
+    fn accepts(&self, file_name: &str) -> bool {
+        file_name == "Cargo.toml"
+    }
+    fn parse(&self, content: &str) -> Result<Vec<Dependency>> {
+        parse_manifest(content)
+    }
+}
+
+///Packages from a registry or git are dependencies, those used by local packages are direct
+fn parse_lock(content: &str) -> Result<Vec<Dependency>> {
+    let lockfile: Lockfile = toml::from_str(content)?;
+    let direct: HashSet<&str> = lockfile
+        .package
+        .iter()
+        .filter(|package| package.source.is_none())
+        .flat_map(|package| &package.dependencies)
+        .filter_map(|dependency| dependency.split_whitespace().next())
+        .collect();
+    Ok(lockfile
+        .package
+        .iter()
+        .filter_map(|package| {
+            let source = package.source.as_deref()?;
+            Some(Dependency {
+                name: package.name.clone(),
+                ecosystem: Ecosystem::Cargo,
+                kind: if direct.contains(package.name.as_str()) {
+                    DependencyKind::Direct
+                } else {
+                    DependencyKind::Transitive
+                },
+                repository: source.strip_prefix("git+").map(str::to_string),
+                purl: None,
+            })
+        })
+        .collect())
+}
+
+///Dependencies of all sections, including `[target.'cfg(..)'.dependencies]` and
+///`[workspace.dependencies]`; path dependencies are skipped
+fn parse_manifest(content: &str) -> Result<Vec<Dependency>> {
+    let manifest: toml::Table = toml::from_str(content)?;
+    let targets = manifest
+        .get("target")
+        .and_then(toml::Value::as_table)
+        .into_iter()
+        .flat_map(|targets| targets.values().filter_map(toml::Value::as_table));
+    let workspace = manifest.get("workspace").and_then(toml::Value::as_table);
+    let tables = std::iter::once(&manifest)
+        .chain(targets)
+        .flat_map(|table| DEPENDENCY_TABLES.iter().filter_map(|name| table.get(*name)))
+        .chain(workspace.and_then(|workspace| workspace.get("dependencies")))
+        .filter_map(toml::Value::as_table);
+    Ok(tables
+        .flat_map(|table| table.iter())
+        .filter(|(_, details)| details.get("path").is_none())
+        .map(|(key, details)| Dependency {
+            name: details
+                .get("package")
+                .and_then(toml::Value::as_str)
+                .unwrap_or(key)
+                .to_string(),
+            ecosystem: Ecosystem::Cargo,
+            kind: DependencyKind::Direct,
+            repository: details
+                .get("git")
+                .and_then(toml::Value::as_str)
+                .map(str::to_string),
+            purl: None,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn dependency(name: &str, kind: DependencyKind) -> Dependency {
+        Dependency {
+            name: name.to_string(),
+            ecosystem: Ecosystem::Cargo,
+            kind,
+            repository: None,
+            purl: None,
+        }
+    }
+    #[test]
+    fn parse_lockfile() {
+        let dependencies = parse_lock(
+            r#"
+            version = 3
+
+            [[package]]
+            name = "app"
+            version = "0.1.0"
+            dependencies = ["serde", "tokio 1.0.0", "helper"]
+
+            [[package]]
+            name = "helper"
+            version = "0.1.0"
+            dependencies = ["log"]
+
+            [[package]]
+            name = "serde"
+            version = "1.0.0"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+            dependencies = ["serde_derive"]
+
+            [[package]]
+            name = "serde_derive"
+            version = "1.0.0"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "log"
+            version = "0.4.0"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "tokio"
+            version = "1.0.0"
+            source = "git+https://github.com/tokio-rs/tokio?branch=master#abcdef"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            dependencies,
+            vec![
+                dependency("serde", DependencyKind::Direct),
+                dependency("serde_derive", DependencyKind::Transitive),
+                dependency("log", DependencyKind::Direct),
+                Dependency {
+                    repository: Some(
+                        "https://github.com/tokio-rs/tokio?branch=master#abcdef".to_string()
+                    ),
+                    ..dependency("tokio", DependencyKind::Direct)
+                },
+            ]
+        );
+    }
+    #[test]
+    fn parse_cargo_toml() {
+        let dependencies = parse_manifest(
+            r#"
+            [package]
+            name = "app"
+
+            [dependencies]
+            serde = "1.0"
+            json = { package = "serde_json", version = "1.0" }
+            local = { path = "../local" }
+
+            [dev-dependencies]
+            wiremock = { git = "https://github.com/LukeMathWalker/wiremock-rs" }
+
+            [target.'cfg(unix)'.dependencies]
+            libc = "0.2"
+            "#,
+        )
+        .unwrap();
+        let names: Vec<&str> = dependencies
+            .iter()
+            .map(|dependency| dependency.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["serde_json", "serde", "wiremock", "libc"]);
+        assert!(dependencies
+            .iter()
+            .all(|dependency| dependency.kind == DependencyKind::Direct));
+        assert_eq!(
+            dependencies[2].repository.as_deref(),
+            Some("https://github.com/LukeMathWalker/wiremock-rs")
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+mod cargo;
+mod npm;
+mod python;
+mod sbom;
+
+///Parsers tried in order, the first one accepting the file name is used
+const PARSERS: [&dyn ManifestParser; 7] = [
+    &cargo::CargoLock,
+    &cargo::CargoManifest,
+    &npm::PackageLock,
+    &npm::YarnLock,
+    &python::PoetryLock,
+    &python::Requirements,
+    &sbom::Sbom,
+];
+
+///Turns a lockfile or manifest of one ecosystem into dependencies
+trait ManifestParser: Sync {
+    ///Whether the parser understands the file with this name
+    fn accepts(&self, file_name: &str) -> bool;
+    fn parse(&self, content: &str) -> Result<Vec<Dependency>>;
+}
+
+///Package the analysed project depends on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dependency {
+    pub name: String,
+    pub ecosystem: Ecosystem,
+    pub kind: DependencyKind,
+    ///Source repository known from the manifest itself, e.g. of git dependencies
+    pub repository: Option<String>,
+    ///Package URL identifying the SBOM component the dependency was read from
+    pub purl: Option<String>,
+}
+
+///Package registry a dependency is published in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Ecosystem {
+    Cargo,
+    Npm,
+    PyPI,
+    ///SBOM component of an ecosystem without registry lookup
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DependencyKind {
+    ///Listed in the manifest of the analysed project
+    Direct,
+    ///Pulled in by another dependency
+    Transitive,
+}
+
+impl fmt::Display for Ecosystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ecosystem::Cargo => write!(f, "cargo"),
+            Ecosystem::Npm => write!(f, "npm"),
+            Ecosystem::PyPI => write!(f, "pypi"),
+            Ecosystem::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+impl fmt::Display for DependencyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DependencyKind::Direct => write!(f, "direct"),
+            DependencyKind::Transitive => write!(f, "transitive"),
+        }
+    }
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.kind)?;
+        if let Some(purl) = &self.purl {
+            write!(f, " [{purl}]")?;
+        }
+        Ok(())
+    }
+}
+
+///Reads dependencies from a supported lockfile or manifest:
+///`Cargo.lock`, `Cargo.toml`, `package-lock.json`, `yarn.lock`, `poetry.lock`, `requirements*.txt`
+///or CycloneDX and SPDX JSON documents
+///# Errors
+///Fails when file can not be read or parsed, or its format is not supported.
+pub fn parse_manifest(path: &Path) -> Result<Vec<Dependency>> {
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let Some(parser) = PARSERS.iter().find(|parser| parser.accepts(file_name)) else {
+        bail!("Unsupported manifest {}", path.display());
+    };
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read manifest {}", path.display()))?;
+    let dependencies = parser
+        .parse(&content)
+        .with_context(|| format!("Could not parse manifest {}", path.display()))?;
+    Ok(deduplicate(dependencies))
+}
+
+///Dependencies of every manifest, each package listed once even when several manifests name it
+///# Errors
+///Fails on the first manifest which can not be read or parsed.
+pub fn parse_manifests(paths: &[PathBuf]) -> Result<Vec<Dependency>> {
+    let dependencies = paths
+        .iter()
+        .map(|path| parse_manifest(path))
+        .flatten_ok()
+        .collect::<Result<Vec<_>>>()?;
+    Ok(deduplicate(dependencies))
+}
+
+///Keeps one entry per package, direct dependencies win over transitive ones.
+///Repository and purl known from any entry are kept.
+fn deduplicate(dependencies: Vec<Dependency>) -> Vec<Dependency> {
+    let mut unique: Vec<Dependency> = vec![];
+    for dependency in dependencies.into_iter().sorted_by(|left, right| {
+        (left.ecosystem, &left.name, left.kind).cmp(&(right.ecosystem, &right.name, right.kind))
+    }) {
+        match unique.last_mut() {
+            Some(kept)
+                if kept.ecosystem == dependency.ecosystem && kept.name == dependency.name =>
+            {
+                kept.repository = kept.repository.take().or(dependency.repository);
+                kept.purl = kept.purl.take().or(dependency.purl);
+            }
+            _ => unique.push(dependency),
+        }
+    }
+    unique
+}
+
+///For lockfiles which do not record what the project itself requires:
+///packages no other package depends on are treated as direct dependencies
+fn kind_by_dependents(name: &str, required: &HashSet<String>) -> DependencyKind {
+    if required.contains(name) {
+        DependencyKind::Transitive
+    } else {
+        DependencyKind::Direct
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn dependency(name: &str, kind: DependencyKind, repository: Option<&str>) -> Dependency {
+        Dependency {
+            name: name.to_string(),
+            ecosystem: Ecosystem::Cargo,
+            kind,
+            repository: repository.map(str::to_string),
+            purl: None,
+        }
+    }
+    #[test]
+    fn deduplicate_across_manifests() {
+        let dependencies = deduplicate(vec![
+            dependency(
+                "serde",
+                DependencyKind::Transitive,
+                Some("git+https://github.com/serde-rs/serde"),
+            ),
+            dependency("log", DependencyKind::Transitive, None),
+            dependency("serde", DependencyKind::Direct, None),
+        ]);
+        assert_eq!(
+            dependencies,
+            vec![
+                dependency("log", DependencyKind::Transitive, None),
+                dependency(
+                    "serde",
+                    DependencyKind::Direct,
+                    Some("git+https://github.com/serde-rs/serde")
+                ),
+            ]
+        );
+    }
+    #[test]
+    fn deduplicate_pypi_names_across_formats() {
+        let mut dependencies = python::Requirements
+            .parse("Typing_Extensions==4.8.0\n")
+            .unwrap();
+        dependencies.extend(
+            sbom::Sbom
+                .parse(
+                    r#"{"bomFormat": "CycloneDX", "components": [
+                        {"bom-ref": "te", "name": "Typing_Extensions", "purl": "pkg:pypi/Typing_Extensions@4.8.0"}
+                    ]}"#,
+                )
+                .unwrap(),
+        );
+        let dependencies = deduplicate(dependencies);
+        assert_eq!(dependencies.len(), 1);
+        assert_eq!(dependencies[0].name, "typing-extensions");
+        assert_eq!(
+            dependencies[0].purl.as_deref(),
+            Some("pkg:pypi/Typing_Extensions@4.8.0")
+        );
+    }
+}
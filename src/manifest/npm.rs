@@ -0,0 +1,368 @@
+use crate::manifest::{kind_by_dependents, Dependency, DependencyKind, Ecosystem, ManifestParser};
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+
+const NODE_MODULES: &str = "node_modules/";
+
+pub(crate) struct PackageLock;
+pub(crate) struct YarnLock;
+
+impl ManifestParser for PackageLock {
+    fn accepts(&self, file_name: &str) -> bool {
+        matches!(file_name, "package-lock.json" | "npm-shrinkwrap.json")
+    }
+    fn parse(&self, content: &str) -> Result<Vec<Dependency>> {
+        let lockfile: PackageLockFile = serde_json::from_str(content)?;
+        if lockfile.packages.is_empty() {
+            Ok(parse_legacy_lock(&lockfile.dependencies))
+        } else {
+            Ok(parse_lock(&lockfile.packages))
+        }
+    }
+}
+
+impl ManifestParser for YarnLock {
+    fn accepts(&self, file_name: &str) -> bool {
+        file_name == "yarn.lock"
+    }
+    fn parse(&self, content: &str) -> Result<Vec<Dependency>> {
+        parse_yarn_lock(content)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct PackageLockFile {
+    ///`lockfileVersion` 2 and 3, keyed by install path, `""` being the project itself
+    #[serde(default)]
+    packages: BTreeMap<String, LockedPackage>,
+    ///`lockfileVersion` 1
+    #[serde(default)]
+    dependencies: BTreeMap<String, LegacyPackage>,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct LockedPackage {
+    ///Real name of aliased packages
+    name: Option<String>,
+    resolved: Option<String>,
+    ///Symlink to a workspace package
+    #[serde(default)]
+    link: bool,
+    #[serde(default)]
+    dependencies: BTreeMap<String, String>,
+    #[serde(default)]
+    dev_dependencies: BTreeMap<String, String>,
+    #[serde(default)]
+    optional_dependencies: BTreeMap<String, String>,
+    #[serde(default)]
+    peer_dependencies: BTreeMap<String, String>,
+}
+#[derive(Deserialize, Debug)]
+struct LegacyPackage {
+    version: String,
+    #[serde(default)]
+    requires: BTreeMap<String, String>,
+    #[serde(default)]
+    dependencies: BTreeMap<String, LegacyPackage>,
+}
+
+///Packages required by the project or its workspaces are direct
+fn parse_lock(packages: &BTreeMap<String, LockedPackage>) -> Vec<Dependency> {
+    let direct: HashSet<&str> = packages
+        .iter()
+        .filter(|(path, _)| !path.contains(NODE_MODULES))
+        .flat_map(|(_, package)| {
+            package
+                .dependencies
+                .keys()
+                .chain(package.dev_dependencies.keys())
+                .chain(package.optional_dependencies.keys())
+                .chain(package.peer_dependencies.keys())
+        })
+        .map(String::as_str)
+        .collect();
+    packages
+        .iter()
+        .filter(|(_, package)| !package.link)
+        .filter_map(|(path, package)| {
+            let (_, installed_as) = path.rsplit_once(NODE_MODULES)?;
+            let name = package.name.as_deref().unwrap_or(installed_as);
+            Some(Dependency {
+                name: name.to_string(),
+                ecosystem: Ecosystem::Npm,
+                kind: if path.strip_prefix(NODE_MODULES) == Some(installed_as)
+                    && direct.contains(installed_as)
+                {
+                    DependencyKind::Direct
+                } else {
+                    DependencyKind::Transitive
+                },
+                repository: package.resolved.as_deref().and_then(git_source),
+                purl: None,
+            })
+        })
+        .collect()
+}
+
+fn parse_legacy_lock(dependencies: &BTreeMap<String, LegacyPackage>) -> Vec<Dependency> {
+    let mut packages = vec![];
+    let mut pending: Vec<(&String, &LegacyPackage)> = dependencies.iter().collect();
+    while let Some((name, package)) = pending.pop() {
+        pending.extend(package.dependencies.iter());
+        packages.push((name, package));
+    }
+    let required: HashSet<String> = packages
+        .iter()
+        .flat_map(|(_, package)| package.requires.keys().cloned())
+        .collect();
+    packages
+        .into_iter()
+        .map(|(name, package)| Dependency {
+            name: name.clone(),
+            ecosystem: Ecosystem::Npm,
+            kind: kind_by_dependents(name, &required),
+            repository: git_source(&package.version),
+            purl: None,
+        })
+        .collect()
+}
+
+///`yarn.lock` of both classic yarn (`version "1.0.0"`) and yarn berry (`version: 1.0.0`).
+///Packages required by yarn berry workspaces are direct, otherwise those nothing else depends on.
+fn parse_yarn_lock(content: &str) -> Result<Vec<Dependency>> {
+    let mut packages: Vec<(String, Option<String>)> = vec![];
+    let mut required = HashSet::new();
+    let mut direct = HashSet::new();
+    let mut entry = YarnEntry::Metadata;
+    let mut in_dependencies = false;
+    for (line_no, line) in content.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let line = line.trim();
+        match indent {
+            0 => {
+                in_dependencies = false;
+                let Some(specifiers) = line.strip_suffix(':') else {
+                    bail!("Invalid yarn.lock entry at line {}", line_no + 1);
+                };
+                if specifiers == "__metadata" {
+                    entry = YarnEntry::Metadata;
+                    continue;
+                }
+                if specifiers.contains("@workspace:") {
+                    entry = YarnEntry::Workspace;
+                    continue;
+                }
+                let specifier = specifiers.split(',').next().unwrap_or_default();
+                let Some(name) = package_name(specifier.trim().trim_matches('"')) else {
+                    bail!("Invalid yarn.lock entry at line {}", line_no + 1);
+                };
+                entry = YarnEntry::Package;
+                packages.push((name.to_string(), None));
+            }
+            2 => {
+                in_dependencies = matches!(
+                    line,
+                    "dependencies:" | "optionalDependencies:" | "peerDependencies:"
+                );
+                let resolved = line
+                    .strip_prefix("resolved ")
+                    .or_else(|| line.strip_prefix("resolution:"));
+                if let (Some(resolved), YarnEntry::Package, Some((_, repository))) =
+                    (resolved, entry, packages.last_mut())
+                {
+                    *repository = git_source(resolved.trim().trim_matches('"'));
+                }
+            }
+            _ if in_dependencies => {
+                let name = line.split_whitespace().next().unwrap_or_default();
+                let name = name.trim_end_matches(':').trim_matches('"').to_string();
+                match entry {
+                    YarnEntry::Workspace => direct.insert(name),
+                    _ => required.insert(name),
+                };
+            }
+            _ => {}
+        }
+    }
+    Ok(packages
+        .into_iter()
+        .map(|(name, repository)| Dependency {
+            kind: if direct.contains(&name) {
+                DependencyKind::Direct
+            } else {
+                kind_by_dependents(&name, &required)
+            },
+            name,
+            ecosystem: Ecosystem::Npm,
+            repository,
+            purl: None,
+        })
+        .collect())
+}
+
+#[derive(Clone, Copy)]
+enum YarnEntry {
+    Metadata,
+    Workspace,
+    Package,
+}
+
+///Name part of `name@range`, keeping the leading `@` of scoped packages
+fn package_name(specifier: &str) -> Option<&str> {
+    let at = specifier.get(1..)?.find('@')? + 1;
+    Some(&specifier[..at])
+}
+
+const GIT_SOURCES: [&str; 4] = ["git+", "git://", "github:", "https://github.com/"];
+
+///Git URL of packages installed straight from a repository, e.g. `git+ssh://git@github.com/o/r.git#sha`,
+///also within yarn berry resolutions such as `name@https://github.com/o/r.git#commit=sha`
+fn git_source(resolved: &str) -> Option<String> {
+    GIT_SOURCES
+        .iter()
+        .filter_map(|prefix| resolved.find(prefix))
+        .min()
+        .map(|start| resolved[start..].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn summary(dependencies: &[Dependency]) -> Vec<(&str, DependencyKind)> {
+        dependencies
+            .iter()
+            .map(|dependency| (dependency.name.as_str(), dependency.kind))
+            .collect()
+    }
+    #[test]
+    fn parse_package_lock() {
+        let dependencies = PackageLock
+            .parse(
+                r#"{
+                "lockfileVersion": 3,
+                "packages": {
+                    "": {"name": "app", "dependencies": {"react": "^18.0.0"}, "devDependencies": {"jest": "^29.0.0"}},
+                    "node_modules/react": {"version": "18.2.0", "resolved": "https://registry.npmjs.org/react/-/react-18.2.0.tgz", "dependencies": {"loose-envify": "^1.1.0"}},
+                    "node_modules/loose-envify": {"version": "1.4.0"},
+                    "node_modules/jest": {"version": "29.0.0", "resolved": "git+ssh://git@github.com/jestjs/jest.git#abcdef"},
+                    "node_modules/jest/node_modules/react": {"version": "17.0.0"},
+                    "node_modules/widgets": {"resolved": "packages/widgets", "link": true},
+                    "packages/widgets": {"dependencies": {"@scope/ui": "^1.0.0"}},
+                    "node_modules/@scope/ui": {"version": "1.0.0"}
+                }
+            }"#,
+            )
+            .unwrap();
+        assert_eq!(
+            summary(&dependencies),
+            vec![
+                ("@scope/ui", DependencyKind::Direct),
+                ("jest", DependencyKind::Direct),
+                ("react", DependencyKind::Transitive),
+                ("loose-envify", DependencyKind::Transitive),
+                ("react", DependencyKind::Direct),
+            ]
+        );
+        assert_eq!(
+            dependencies[1].repository.as_deref(),
+            Some("git+ssh://git@github.com/jestjs/jest.git#abcdef")
+        );
+        assert_eq!(dependencies[4].repository, None);
+    }
+    #[test]
+    fn parse_legacy_package_lock() {
+        let dependencies = PackageLock
+            .parse(
+                r#"{
+                "lockfileVersion": 1,
+                "dependencies": {
+                    "express": {"version": "4.18.0", "requires": {"debug": "2.6.9"}},
+                    "debug": {"version": "2.6.9"}
+                }
+            }"#,
+            )
+            .unwrap();
+        assert_eq!(
+            summary(&dependencies),
+            vec![
+                ("express", DependencyKind::Direct),
+                ("debug", DependencyKind::Transitive),
+            ]
+        );
+    }
+    #[test]
+    fn parse_classic_yarn_lock() {
+        let dependencies = YarnLock
+            .parse(
+                r#"# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.
+# yarn lockfile v1
+
+
+"@babel/code-frame@^7.0.0", "@babel/code-frame@^7.10.4":
+  version "7.10.4"
+  resolved "https://registry.yarnpkg.com/@babel/code-frame/-/code-frame-7.10.4.tgz"
+  dependencies:
+    "@babel/highlight" "^7.10.4"
+
+"@babel/highlight@^7.10.4":
+  version "7.10.4"
+
+left-pad@github:stevemao/left-pad:
+  version "1.3.0"
+  resolved "git+https://github.com/stevemao/left-pad.git#abcdef"
+"#,
+            )
+            .unwrap();
+        assert_eq!(
+            summary(&dependencies),
+            vec![
+                ("@babel/code-frame", DependencyKind::Direct),
+                ("@babel/highlight", DependencyKind::Transitive),
+                ("left-pad", DependencyKind::Direct),
+            ]
+        );
+        assert_eq!(
+            dependencies[2].repository.as_deref(),
+            Some("git+https://github.com/stevemao/left-pad.git#abcdef")
+        );
+    }
+    #[test]
+    fn parse_berry_yarn_lock() {
+        let dependencies = YarnLock
+            .parse(
+                r#"__metadata:
+  version: 6
+
+"app@workspace:.":
+  version: 0.0.0-use.local
+  dependencies:
+    lodash: ^4.17.21
+
+"lodash@npm:^4.17.21":
+  version: 4.17.21
+  resolution: "lodash@npm:4.17.21"
+  dependencies:
+    "@types/node": ^20.0.0
+
+"@types/node@npm:^20.0.0":
+  version: 20.0.0
+  resolution: "@types/node@npm:20.0.0"
+"#,
+            )
+            .unwrap();
+        assert_eq!(
+            summary(&dependencies),
+            vec![
+                ("lodash", DependencyKind::Direct),
+                ("@types/node", DependencyKind::Transitive),
+            ]
+        );
+        assert!(dependencies
+            .iter()
+            .all(|dependency| dependency.repository.is_none()));
+    }
+}
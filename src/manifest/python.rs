@@ -0,0 +1,260 @@
+use crate::manifest::{kind_by_dependents, Dependency, DependencyKind, Ecosystem, ManifestParser};
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+pub(crate) struct PoetryLock;
+pub(crate) struct Requirements;
+
+impl ManifestParser for PoetryLock {
+    fn accepts(&self, file_name: &str) -> bool {
+        file_name == "poetry.lock"
+    }
+    fn parse(&self, content: &str) -> Result<Vec<Dependency>> {
+        parse_poetry_lock(content)
+    }
+}
+
+impl ManifestParser for Requirements {
+    fn accepts(&self, file_name: &str) -> bool {
+        file_name.starts_with("requirements") && file_name.ends_with(".txt")
+    }
+    fn parse(&self, content: &str) -> Result<Vec<Dependency>> {
+        Ok(parse_requirements(content))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct PoetryLockFile {
+    #[serde(default)]
+    package: Vec<PoetryPackage>,
+}
+#[derive(Deserialize, Debug)]
+struct PoetryPackage {
+    name: String,
+    #[serde(default)]
+    dependencies: HashMap<String, toml::Value>,
+    source: Option<PoetrySource>,
+}
+#[derive(Deserialize, Debug)]
+struct PoetrySource {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+}
+
+///`poetry.lock` does not record what the project requires, packages nothing depends on are direct
+fn parse_poetry_lock(content: &str) -> Result<Vec<Dependency>> {
+    let lockfile: PoetryLockFile = toml::from_str(content)?;
+    let required: HashSet<String> = lockfile
+        .package
+        .iter()
+        .flat_map(|package| package.dependencies.keys())
+        .map(|name| normalize(name))
+        .collect();
+    Ok(lockfile
+        .package
+        .into_iter()
+        .map(|package| {
+            let name = normalize(&package.name);
+            Dependency {
+                kind: kind_by_dependents(&name, &required),
+                name,
+                ecosystem: Ecosystem::PyPI,
+                repository: package
+                    .source
+                    .filter(|source| source.kind == "git")
+                    .map(|source| source.url),
+                purl: None,
+            }
+        })
+        .collect())
+}
+
+///Every requirement is direct; options such as `-r` or `--index-url` are skipped,
+///editable, `name @ url` and bare git URL requirements keep their git URL
+fn parse_requirements(content: &str) -> Vec<Dependency> {
+    content
+        .replace("\\\n", " ")
+        .lines()
+        .filter_map(|line| {
+            let line = line.split(" #").next().unwrap_or_default().trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let requirement = line
+                .strip_prefix("-e ")
+                .or_else(|| line.strip_prefix("--editable "))
+                .map(str::trim)
+                .unwrap_or(line);
+            if requirement.starts_with('-') {
+                return None;
+            }
+            //PEP 508 direct reference `name @ url`, spaces around `@` are optional
+            if let Some((name, url)) = requirement
+                .split_once('@')
+                .filter(|(name, url)| !name.contains("://") && url.contains("://"))
+            {
+                let url = url.trim().split([' ', ';']).next()?;
+                return Some(requirement_dependency(requirement_name(name)?, Some(url)));
+            }
+            if requirement.contains("://") {
+                let url = requirement.split_whitespace().next()?;
+                let name = match url.split_once("#egg=") {
+                    Some((_, egg)) => requirement_name(egg),
+                    None => git_repository_name(url),
+                };
+                if name.is_none() {
+                    log::warn!("Skipping requirement {url}, its package name is unknown");
+                }
+                return Some(requirement_dependency(name?, Some(url)));
+            }
+            Some(requirement_dependency(requirement_name(requirement)?, None))
+        })
+        .collect()
+}
+
+///Project name of a requirement, without extras, version specifiers or markers
+fn requirement_name(requirement: &str) -> Option<&str> {
+    requirement
+        .trim()
+        .split(['[', '<', '>', '=', '!', '~', ';', ' '])
+        .next()
+        .filter(|name| !name.is_empty())
+}
+
+///Repository name of a git URL without `#egg=`, e.g. `tools` of `git+https://github.com/acme/tools.git@main`
+fn git_repository_name(url: &str) -> Option<&str> {
+    if !url.starts_with("git+") {
+        return None;
+    }
+    let path = url.split('#').next()?.trim_end_matches('/');
+    let name = path.rsplit('/').next()?.split('@').next()?;
+    Some(name.strip_suffix(".git").unwrap_or(name)).filter(|name| !name.is_empty())
+}
+
+fn requirement_dependency(name: &str, url: Option<&str>) -> Dependency {
+    Dependency {
+        name: normalize(name),
+        ecosystem: Ecosystem::PyPI,
+        kind: DependencyKind::Direct,
+        repository: url
+            .filter(|url| url.starts_with("git+"))
+            .map(|url| url.split('#').next().unwrap_or(url).to_string()),
+        purl: None,
+    }
+}
+
+///PEP 503 normalized project name, e.g. `Typing_Extensions` -> `typing-extensions`
+pub(super) fn normalize(name: &str) -> String {
+    name.split(['-', '_', '.'])
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn summary(dependencies: &[Dependency]) -> Vec<(&str, DependencyKind, Option<&str>)> {
+        dependencies
+            .iter()
+            .map(|dependency| {
+                (
+                    dependency.name.as_str(),
+                    dependency.kind,
+                    dependency.repository.as_deref(),
+                )
+            })
+            .collect()
+    }
+    #[test]
+    fn parse_poetry() {
+        let dependencies = PoetryLock
+            .parse(
+                r#"
+                [[package]]
+                name = "requests"
+                version = "2.31.0"
+
+                [package.dependencies]
+                certifi = ">=2017.4.17"
+                charset-normalizer = {version = ">=2,<4"}
+
+                [[package]]
+                name = "certifi"
+                version = "2023.7.22"
+
+                [[package]]
+                name = "Charset_Normalizer"
+                version = "3.2.0"
+
+                [[package]]
+                name = "internal-tools"
+                version = "0.1.0"
+
+                [package.source]
+                type = "git"
+                url = "https://github.com/acme/internal-tools.git"
+                reference = "main"
+                "#,
+            )
+            .unwrap();
+        assert_eq!(
+            summary(&dependencies),
+            vec![
+                ("requests", DependencyKind::Direct, None),
+                ("certifi", DependencyKind::Transitive, None),
+                ("charset-normalizer", DependencyKind::Transitive, None),
+                (
+                    "internal-tools",
+                    DependencyKind::Direct,
+                    Some("https://github.com/acme/internal-tools.git")
+                ),
+            ]
+        );
+    }
+    #[test]
+    fn parse_requirements_txt() {
+        let dependencies = Requirements
+            .parse(
+                "# production\n\
+             -r base.txt\n\
+             --index-url https://pypi.org/simple\n\
+             Django>=4.2,<5 # web\n\
+             requests[socks]==2.31.0 \\\n    --hash=sha256:abc\n\
+             typing_extensions; python_version < \"3.10\"\n\
+             -e git+https://github.com/acme/tools.git@main#egg=acme-tools\n\
+             flask @ git+https://github.com/pallets/flask.git@2.3.0\n\
+             click@ https://github.com/pallets/click/archive/8.1.7.zip ; python_version >= \"3.8\"\n\
+             git+https://github.com/psf/black.git@24.1.0\n\
+             https://example.com/packages/unnamed-1.0.tar.gz\n",
+            )
+            .unwrap();
+        assert_eq!(
+            summary(&dependencies),
+            vec![
+                ("django", DependencyKind::Direct, None),
+                ("requests", DependencyKind::Direct, None),
+                ("typing-extensions", DependencyKind::Direct, None),
+                (
+                    "acme-tools",
+                    DependencyKind::Direct,
+                    Some("git+https://github.com/acme/tools.git@main")
+                ),
+                (
+                    "flask",
+                    DependencyKind::Direct,
+                    Some("git+https://github.com/pallets/flask.git@2.3.0")
+                ),
+                ("click", DependencyKind::Direct, None),
+                (
+                    "black",
+                    DependencyKind::Direct,
+                    Some("git+https://github.com/psf/black.git@24.1.0")
+                ),
+            ]
+        );
+    }
+}
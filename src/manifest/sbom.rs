@@ -0,0 +1,427 @@
+use crate::manifest::{
+    kind_by_dependents, python, Dependency, DependencyKind, Ecosystem, ManifestParser,
+};
+use anyhow::{bail, Result};
+use percent_encoding::percent_decode_str;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+///CycloneDX or SPDX JSON document, told apart by `bomFormat` and `spdxVersion`
+pub(crate) struct Sbom;
+
+impl ManifestParser for Sbom {
+    fn accepts(&self, file_name: &str) -> bool {
+        file_name.ends_with(".json")
+    }
+    fn parse(&self, content: &str) -> Result<Vec<Dependency>> {
+        let document: serde_json::Value = serde_json::from_str(content)?;
+        if document
+            .get("bomFormat")
+            .and_then(serde_json::Value::as_str)
+            == Some("CycloneDX")
+        {
+            Ok(parse_cyclonedx(serde_json::from_value(document)?))
+        } else if document.get("spdxVersion").is_some() {
+            Ok(parse_spdx(serde_json::from_value(document)?))
+        } else {
+            bail!("Neither a CycloneDX nor an SPDX document")
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct CycloneDxDocument {
+    metadata: Option<CycloneDxMetadata>,
+    #[serde(default)]
+    components: Vec<CycloneDxComponent>,
+    #[serde(default)]
+    dependencies: Vec<CycloneDxDependency>,
+}
+#[derive(Deserialize, Debug)]
+struct CycloneDxMetadata {
+    ///The analysed project itself
+    component: Option<CycloneDxComponent>,
+}
+#[derive(Deserialize, Debug)]
+struct CycloneDxComponent {
+    #[serde(rename = "bom-ref")]
+    bom_ref: Option<String>,
+    group: Option<String>,
+    name: String,
+    purl: Option<String>,
+    #[serde(default, rename = "externalReferences")]
+    external_references: Vec<CycloneDxReference>,
+    ///Sub-components, e.g. packages bundled in an assembly
+    #[serde(default)]
+    components: Vec<CycloneDxComponent>,
+}
+#[derive(Deserialize, Debug)]
+struct CycloneDxReference {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxDependency {
+    #[serde(rename = "ref")]
+    reference: String,
+    #[serde(default)]
+    depends_on: Vec<String>,
+}
+
+///Components with `vcs` external references keep them as repository,
+///dependencies of the metadata component are direct
+fn parse_cyclonedx(document: CycloneDxDocument) -> Vec<Dependency> {
+    let roots: HashSet<String> = document
+        .metadata
+        .and_then(|metadata| metadata.component)
+        .and_then(|component| component.bom_ref)
+        .into_iter()
+        .collect();
+    let edges: Vec<(&str, &str)> = document
+        .dependencies
+        .iter()
+        .flat_map(|dependency| {
+            dependency
+                .depends_on
+                .iter()
+                .map(|target| (dependency.reference.as_str(), target.as_str()))
+        })
+        .collect();
+    let graph = DependencyGraph::new(&roots, &edges);
+    let mut pending: Vec<&CycloneDxComponent> = document.components.iter().collect();
+    let mut dependencies = vec![];
+    while let Some(component) = pending.pop() {
+        pending.extend(component.components.iter());
+        let name = match &component.group {
+            Some(group) if !group.is_empty() => format!("{group}/{}", component.name),
+            _ => component.name.clone(),
+        };
+        let repository = component
+            .external_references
+            .iter()
+            .find(|reference| reference.kind == "vcs")
+            .map(|reference| reference.url.clone());
+        let id = component.bom_ref.as_deref().unwrap_or(&name);
+        dependencies.push(component_dependency(
+            name.clone(),
+            graph.kind(id),
+            repository,
+            component.purl.clone(),
+        ));
+    }
+    dependencies
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SpdxDocument {
+    #[serde(default)]
+    document_describes: Vec<String>,
+    #[serde(default)]
+    packages: Vec<SpdxPackage>,
+    #[serde(default)]
+    relationships: Vec<SpdxRelationship>,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SpdxPackage {
+    #[serde(rename = "SPDXID")]
+    spdx_id: String,
+    name: String,
+    download_location: Option<String>,
+    #[serde(default)]
+    external_refs: Vec<SpdxExternalRef>,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SpdxExternalRef {
+    reference_type: String,
+    reference_locator: String,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SpdxRelationship {
+    spdx_element_id: String,
+    relationship_type: String,
+    related_spdx_element: String,
+}
+
+///Packages described by the document are the analysed project, VCS download locations
+///(e.g. `git+https://github.com/o/r@v1.0`) are kept as repository
+fn parse_spdx(document: SpdxDocument) -> Vec<Dependency> {
+    let roots: HashSet<String> = document
+        .document_describes
+        .iter()
+        .cloned()
+        .chain(
+            document
+                .relationships
+                .iter()
+                .filter(|relationship| relationship.relationship_type == "DESCRIBES")
+                .map(|relationship| relationship.related_spdx_element.clone()),
+        )
+        .collect();
+    let edges: Vec<(&str, &str)> = document
+        .relationships
+        .iter()
+        .filter_map(|relationship| {
+            let from = relationship.spdx_element_id.as_str();
+            let to = relationship.related_spdx_element.as_str();
+            match relationship.relationship_type.as_str() {
+                "DEPENDS_ON" => Some((from, to)),
+                "DEPENDENCY_OF" => Some((to, from)),
+                _ => None,
+            }
+        })
+        .collect();
+    let graph = DependencyGraph::new(&roots, &edges);
+    document
+        .packages
+        .iter()
+        .filter(|package| !roots.contains(&package.spdx_id))
+        .map(|package| {
+            let purl = package
+                .external_refs
+                .iter()
+                .find(|reference| reference.reference_type == "purl")
+                .map(|reference| reference.reference_locator.clone());
+            let repository = package
+                .download_location
+                .as_deref()
+                .filter(|location| is_vcs_location(location))
+                .map(str::to_string);
+            component_dependency(
+                package.name.clone(),
+                graph.kind(&package.spdx_id),
+                repository,
+                purl,
+            )
+        })
+        .collect()
+}
+
+///Which components the analysed project depends on, falling back to
+///components nothing depends on when the document has no edges from the project
+struct DependencyGraph<'a> {
+    direct: HashSet<&'a str>,
+    required: HashSet<String>,
+}
+
+impl<'a> DependencyGraph<'a> {
+    fn new(roots: &HashSet<String>, edges: &[(&'a str, &'a str)]) -> Self {
+        DependencyGraph {
+            direct: edges
+                .iter()
+                .filter(|(from, _)| roots.contains(*from))
+                .map(|(_, to)| *to)
+                .collect(),
+            required: edges
+                .iter()
+                .filter(|(from, _)| !roots.contains(*from))
+                .map(|(_, to)| to.to_string())
+                .collect(),
+        }
+    }
+    fn kind(&self, id: &str) -> DependencyKind {
+        if self.direct.is_empty() {
+            kind_by_dependents(id, &self.required)
+        } else if self.direct.contains(id) {
+            DependencyKind::Direct
+        } else {
+            DependencyKind::Transitive
+        }
+    }
+}
+
+///Registry name and ecosystem come from the purl when it names a supported registry,
+///so that components without VCS reference can still be looked up
+fn component_dependency(
+    name: String,
+    kind: DependencyKind,
+    repository: Option<String>,
+    purl: Option<String>,
+) -> Dependency {
+    let (ecosystem, name) = purl
+        .as_deref()
+        .and_then(purl_package)
+        .unwrap_or((Ecosystem::Unknown, name));
+    Dependency {
+        name,
+        ecosystem,
+        kind,
+        repository,
+        purl,
+    }
+}
+
+///Ecosystem and registry name of `pkg:type/namespace/name@version?qualifiers#subpath`
+fn purl_package(purl: &str) -> Option<(Ecosystem, String)> {
+    let path = purl.strip_prefix("pkg:")?.trim_start_matches('/');
+    let path = path.split(['?', '#']).next()?;
+    let (kind, path) = path.split_once('/')?;
+    let ecosystem = match kind.to_lowercase().as_str() {
+        "cargo" => Ecosystem::Cargo,
+        "npm" => Ecosystem::Npm,
+        "pypi" => Ecosystem::PyPI,
+        _ => return None,
+    };
+    let (namespace, name) = match path.rsplit_once('/') {
+        Some((namespace, name)) => (Some(namespace), name),
+        None => (None, path),
+    };
+    let name = name.split('@').next().filter(|name| !name.is_empty())?;
+    let name = match namespace {
+        Some(namespace) => format!("{namespace}/{name}"),
+        None => name.to_string(),
+    };
+    let name = percent_decode_str(&name).decode_utf8().ok()?;
+    //Named like in requirements files, so that both end up as one dependency
+    let name = match ecosystem {
+        Ecosystem::PyPI => python::normalize(&name),
+        _ => name.into_owned(),
+    };
+    Some((ecosystem, name))
+}
+
+///SPDX download locations of the form `<vcs>+<transport>://...` or `git://...`,
+///as opposed to package archives or `NOASSERTION`
+fn is_vcs_location(location: &str) -> bool {
+    location
+        .split_once("://")
+        .is_some_and(|(scheme, _)| scheme == "git" || scheme.contains('+'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn summary(
+        dependencies: &[Dependency],
+    ) -> Vec<(&str, Ecosystem, DependencyKind, Option<&str>)> {
+        dependencies
+            .iter()
+            .map(|dependency| {
+                (
+                    dependency.name.as_str(),
+                    dependency.ecosystem,
+                    dependency.kind,
+                    dependency.repository.as_deref(),
+                )
+            })
+            .collect()
+    }
+    #[test]
+    fn parse_cyclonedx_document() {
+        let dependencies = Sbom
+            .parse(
+                r#"{
+                "bomFormat": "CycloneDX",
+                "specVersion": "1.5",
+                "metadata": {"component": {"bom-ref": "app", "name": "app"}},
+                "components": [
+                    {"bom-ref": "react", "name": "react", "purl": "pkg:npm/react@18.2.0",
+                     "externalReferences": [
+                        {"type": "website", "url": "https://react.dev"},
+                        {"type": "vcs", "url": "https://github.com/facebook/react"}
+                     ]},
+                    {"bom-ref": "ui", "group": "@scope", "name": "ui", "purl": "pkg:npm/%40scope/ui@1.0.0"},
+                    {"bom-ref": "zlib", "name": "zlib", "purl": "pkg:generic/zlib@1.3",
+                     "externalReferences": [{"type": "vcs", "url": "https://github.com/madler/zlib"}]}
+                ],
+                "dependencies": [
+                    {"ref": "app", "dependsOn": ["react", "zlib"]},
+                    {"ref": "react", "dependsOn": ["ui"]}
+                ]
+            }"#,
+            )
+            .unwrap();
+        assert_eq!(
+            summary(&dependencies),
+            vec![
+                (
+                    "zlib",
+                    Ecosystem::Unknown,
+                    DependencyKind::Direct,
+                    Some("https://github.com/madler/zlib")
+                ),
+                (
+                    "@scope/ui",
+                    Ecosystem::Npm,
+                    DependencyKind::Transitive,
+                    None
+                ),
+                (
+                    "react",
+                    Ecosystem::Npm,
+                    DependencyKind::Direct,
+                    Some("https://github.com/facebook/react")
+                ),
+            ]
+        );
+        assert_eq!(
+            dependencies[2].purl.as_deref(),
+            Some("pkg:npm/react@18.2.0")
+        );
+    }
+    #[test]
+    fn parse_spdx_document() {
+        let dependencies = Sbom
+            .parse(
+                r#"{
+                "spdxVersion": "SPDX-2.3",
+                "SPDXID": "SPDXRef-DOCUMENT",
+                "packages": [
+                    {"SPDXID": "SPDXRef-app", "name": "app", "downloadLocation": "NOASSERTION"},
+                    {"SPDXID": "SPDXRef-serde", "name": "serde", "downloadLocation": "git+https://github.com/serde-rs/serde@v1.0.0",
+                     "externalRefs": [{"referenceCategory": "PACKAGE-MANAGER", "referenceType": "purl", "referenceLocator": "pkg:cargo/serde@1.0.0"}]},
+                    {"SPDXID": "SPDXRef-requests", "name": "requests", "downloadLocation": "https://files.pythonhosted.org/requests-2.31.0.tar.gz",
+                     "externalRefs": [{"referenceCategory": "PACKAGE_MANAGER", "referenceType": "purl", "referenceLocator": "pkg:pypi/requests@2.31.0"}]}
+                ],
+                "relationships": [
+                    {"spdxElementId": "SPDXRef-DOCUMENT", "relationshipType": "DESCRIBES", "relatedSpdxElement": "SPDXRef-app"},
+                    {"spdxElementId": "SPDXRef-app", "relationshipType": "DEPENDS_ON", "relatedSpdxElement": "SPDXRef-serde"},
+                    {"spdxElementId": "SPDXRef-requests", "relationshipType": "DEPENDENCY_OF", "relatedSpdxElement": "SPDXRef-serde"}
+                ]
+            }"#,
+            )
+            .unwrap();
+        assert_eq!(
+            summary(&dependencies),
+            vec![
+                (
+                    "serde",
+                    Ecosystem::Cargo,
+                    DependencyKind::Direct,
+                    Some("git+https://github.com/serde-rs/serde@v1.0.0")
+                ),
+                (
+                    "requests",
+                    Ecosystem::PyPI,
+                    DependencyKind::Transitive,
+                    None
+                ),
+            ]
+        );
+        assert_eq!(
+            dependencies[1].purl.as_deref(),
+            Some("pkg:pypi/requests@2.31.0")
+        );
+    }
+    #[test]
+    fn decode_purl_names() {
+        assert_eq!(
+            purl_package("pkg:npm/%40scope/ui%2Bkit@1.0.0?arch=x86#lib"),
+            Some((Ecosystem::Npm, "@scope/ui+kit".to_string()))
+        );
+        assert_eq!(
+            purl_package("pkg:pypi/Zope%2einterface@6.0"),
+            Some((Ecosystem::PyPI, "zope-interface".to_string()))
+        );
+        assert_eq!(purl_package("pkg:cargo/broken%FF@1.0"), None);
+    }
+    #[test]
+    fn reject_other_json() {
+        assert!(Sbom.parse(r#"{"name": "app"}"#).is_err());
+    }
+}
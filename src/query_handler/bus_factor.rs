@@ -1,95 +1,398 @@
-use crate::query_handler::{BusFactorQueryResult, Other, RepositoryQueryResult};
-use anyhow::{anyhow, Context, Result};
+use crate::query_handler::contributor_stats::ContributorStatsHandler;
+use crate::query_handler::truck_factor::TruckFactorHandler;
+use crate::query_handler::{
+    ensure_contributor_data, next_page_url, BusFactorQueryResult, ConcentrationMetrics,
+    DominantContributor, NoDataReason, Other, RepositoryQueryResult,
+};
+use crate::{
+    AliasMap, BotPolicy, BusFactorQueryCommand, ContributionWeight, ContributorList,
+    DetectionStrategy, ForgeProvider, TimeWindow,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use itertools::Itertools;
 use reqwest::Url;
 use serde::Deserialize;
-use tokio::sync::mpsc::{channel, Receiver, Sender};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::task::{JoinHandle, JoinSet};
+
+const CONTRIBUTORS_PAGE_SIZE: &str = "100";
 
 pub(crate) struct ContributorHandler;
 impl ContributorHandler {
-    pub(crate) async fn run(
+    ///Repositories are analysed concurrently, the first failing repository stops the stage
+    pub(crate) fn run<P: ForgeProvider>(
         mut receiver: Receiver<RepositoryQueryResult>,
-    ) -> Result<Receiver<BusFactorQueryResult>> {
-        let (tx, rx) = channel(1000);
-        tokio::task::spawn(async move {
-            while let Some(data) = receiver.recv().await {
-                tokio::spawn(Self::fetch_data(data, tx.clone()));
+        provider: Arc<P>,
+        tx: Sender<BusFactorQueryResult>,
+    ) -> JoinHandle<Result<()>> {
+        let stage = tokio::task::spawn(async move {
+            let mut requests = JoinSet::new();
+            loop {
+                tokio::select! {
+                    data = receiver.recv() => match data {
+                        Some(data) => {
+                            requests.spawn(Self::query_api(data, provider.clone(), tx.clone()));
+                        }
+                        None => break,
+                    },
+                    Some(request) = requests.join_next() => request??,
+                }
+            }
+            while let Some(request) = requests.join_next().await {
+                request??;
             }
+            Ok(())
         });
         log::debug!("ContributorHandler initialised");
-        Ok(rx)
-    }
-
-    async fn fetch_data(data: RepositoryQueryResult, tx: Sender<BusFactorQueryResult>) {
-        if let Err(err) = Self::query_api(data, tx).await {
-            eprintln!("Error during fetching contributors API: {err}");
-            std::process::exit(1);
-        }
+        stage
     }
-    async fn query_api(
+    ///`NoDataReason` errors are reported for the repository, anything else fails the stage
+    async fn query_api<P: ForgeProvider>(
         data: RepositoryQueryResult,
+        provider: Arc<P>,
         tx: Sender<BusFactorQueryResult>,
     ) -> Result<()> {
-        let response = Self::fetch_page_of_results(&data).await?;
-        let bus_factor_detected =
-            Self::detect_bus_factor(&response.contributors, &data.project_name, data.stargazers);
-        if let Some(bus_factor) = bus_factor_detected {
-            tx.send(bus_factor).await?;
-        }
+        let bus_factor = match Self::calculate(&data, provider.as_ref()).await {
+            Ok(bus_factor) => bus_factor,
+            Err(err) => {
+                let reason = err
+                    .downcast::<NoDataReason>()
+                    .context("Error during fetching contributors API")?;
+                log::warn!("No contributor data for {}: {reason}", data.project_name);
+                BusFactorQueryResult::no_data(&data, reason)
+            }
+        };
+        tx.send(bus_factor).await?;
         Ok(())
     }
-    fn detect_bus_factor(
-        contributors: &[ContributorDetails],
+    async fn calculate<P: ForgeProvider>(
+        data: &RepositoryQueryResult,
+        provider: &P,
+    ) -> Result<BusFactorQueryResult> {
+        let command = &data.client_details.command;
+        match command.strategy {
+            DetectionStrategy::Commits => {
+                let ContributorList {
+                    contributors,
+                    truncated,
+                } = provider.contributors(data).await?;
+                if contributors.is_empty() {
+                    bail!(NoDataReason::EmptyRepository);
+                }
+                let contributors = Self::prepare(contributors, command);
+                if contributors.is_empty() {
+                    bail!(NoDataReason::OnlyBots);
+                }
+                let authors = Self::weigh(&contributors, command);
+                if Self::calculate_weight_sum(&authors) == 0 {
+                    bail!(NoDataReason::NoActivity);
+                }
+                Ok(BusFactorQueryResult {
+                    language: data.language.clone(),
+                    dependencies: data.dependencies.clone(),
+                    truncated,
+                    ..Self::calculate_bus_factor(
+                        &authors,
+                        &data.project_name,
+                        data.stargazers,
+                        command,
+                    )
+                })
+            }
+            DetectionStrategy::TruckFactor => {
+                if !provider.supports_file_histories() {
+                    bail!(NoDataReason::Unsupported);
+                }
+                TruckFactorHandler::calculate(data, provider.file_histories(data).await?)
+            }
+        }
+    }
+    fn calculate_bus_factor(
+        authors: &[AuthorWeight],
         project_name: &str,
         star_gazers: u32,
-    ) -> Option<BusFactorQueryResult> {
+        command: &BusFactorQueryCommand,
+    ) -> BusFactorQueryResult {
         log::trace!("Calculating bus factor for {project_name}");
-        let contributors_total_commits = Self::calculate_commits_sum(contributors);
-        contributors.iter().find_map(|contributor| {
-            let percentage = (100 * contributor.contributions) / contributors_total_commits;
-            log::trace!("Calculated percentage is {percentage:?} for project {project_name}");
-            if percentage >= 75 {
-                log::info!("Project {project_name} has a busfactor");
-                return Some(BusFactorQueryResult {
-                    login: contributor.login.clone(),
-                    contributions: contributor.contributions,
-                    repo_name: project_name.to_string(),
-                    bus_factor: percentage,
-                    stargazers: star_gazers,
-                });
+        let total_weight = Self::calculate_weight_sum(authors);
+        let mut covered_weight: u64 = 0;
+        let key_contributors: Vec<String> = authors
+            .iter()
+            .sorted_by_key(|author| Reverse(author.weight))
+            .take_while(|author| {
+                //Widened as line weights of large repositories overflow when multiplied
+                let covered_enough = 100 * u128::from(covered_weight)
+                    >= u128::from(command.coverage) * u128::from(total_weight);
+                covered_weight = covered_weight.saturating_add(author.weight);
+                !covered_enough
+            })
+            .map(|author| author.identity.clone())
+            .collect();
+        log::trace!(
+            "Project {project_name} has bus factor {} at {}% coverage",
+            key_contributors.len(),
+            command.coverage
+        );
+        let dominant_contributors = Self::detect_bus_factor(authors, project_name, command);
+        let concentration = Self::concentration(authors.iter().map(|author| author.weight));
+        BusFactorQueryResult {
+            repo_name: project_name.to_string(),
+            language: String::new(),
+            dependencies: vec![],
+            stargazers: star_gazers,
+            bus_factor: key_contributors.len() as u32,
+            key_contributors,
+            flagged: Self::is_flagged(&dominant_contributors, &concentration, command),
+            dominant_contributors,
+            truncated: false,
+            concentration,
+            no_data: None,
+        }
+    }
+    ///Distribution metrics over all contributors
+    pub(crate) fn concentration(weights: impl Iterator<Item = u64>) -> ConcentrationMetrics {
+        let weights: Vec<u64> = weights.filter(|weight| *weight > 0).sorted().collect();
+        if weights.is_empty() {
+            return ConcentrationMetrics::default();
+        }
+        let contributors = weights.len();
+        let total: f64 = weights.iter().map(|weight| *weight as f64).sum();
+        let shares: Vec<f64> = weights
+            .iter()
+            .map(|weight| *weight as f64 / total)
+            .collect();
+        let ranked_weight_sum: f64 = weights
+            .iter()
+            .enumerate()
+            .map(|(rank, weight)| (rank + 1) as f64 * *weight as f64)
+            .sum();
+        let n = contributors as f64;
+        ConcentrationMetrics {
+            contributors,
+            gini: 2.0 * ranked_weight_sum / (n * total) - (n + 1.0) / n,
+            hhi: shares.iter().map(|share| share * share).sum(),
+            entropy: -shares.iter().map(|share| share * share.log2()).sum::<f64>(),
+            top3_share: shares.iter().rev().take(3).sum(),
+        }
+    }
+    pub(crate) fn is_flagged(
+        dominant_contributors: &[DominantContributor],
+        concentration: &ConcentrationMetrics,
+        command: &BusFactorQueryCommand,
+    ) -> bool {
+        match concentration.score(command.criterion) {
+            Some(score) => score >= f64::from(command.threshold),
+            None => !dominant_contributors.is_empty(),
+        }
+    }
+    ///Every contributor owning at least `threshold` percent, ranked by their share
+    pub(crate) fn detect_bus_factor(
+        authors: &[AuthorWeight],
+        project_name: &str,
+        command: &BusFactorQueryCommand,
+    ) -> Vec<DominantContributor> {
+        let total_weight = Self::calculate_weight_sum(authors);
+        if total_weight == 0 {
+            return vec![];
+        }
+        let dominant_contributors: Vec<DominantContributor> = authors
+            .iter()
+            .filter(|author| !(command.bot_policy == BotPolicy::ExcludeFromDominant && author.bot))
+            .filter_map(|author| {
+                let percentage =
+                    (100 * u128::from(author.weight) / u128::from(total_weight)) as u32;
+                log::trace!("Calculated percentage is {percentage:?} for project {project_name}");
+                (percentage >= command.threshold).then(|| DominantContributor {
+                    login: author.identity.clone(),
+                    contributions: author.weight,
+                    percentage,
+                })
+            })
+            .sorted_by(|first, second| {
+                second
+                    .contributions
+                    .cmp(&first.contributions)
+                    .then_with(|| first.login.cmp(&second.login))
+            })
+            .collect();
+        if !dominant_contributors.is_empty() {
+            log::info!(
+                "Project {project_name} has {} dominant contributor(s)",
+                dominant_contributors.len()
+            );
+        }
+        dominant_contributors
+    }
+
+    ///Automation account detected by its account type or `[bot]` suffix,
+    ///unless overridden by user supplied allow/deny lists
+    pub(crate) fn is_bot(
+        identity: &str,
+        bot_account: bool,
+        command: &BusFactorQueryCommand,
+    ) -> bool {
+        if command.allow_logins.iter().any(|login| login == identity) {
+            return false;
+        }
+        if command.deny_logins.iter().any(|login| login == identity) {
+            return true;
+        }
+        bot_account || identity.ends_with("[bot]")
+    }
+
+    ///Aliases are folded first, so that the bot policy judges the canonical identity
+    fn prepare(
+        contributors: Vec<ContributorDetails>,
+        command: &BusFactorQueryCommand,
+    ) -> Vec<ContributorDetails> {
+        let mut contributors = Self::fold_aliases(contributors, &command.aliases);
+        Self::filter_bots(&mut contributors, command);
+        contributors
+    }
+
+    fn filter_bots(contributors: &mut Vec<ContributorDetails>, command: &BusFactorQueryCommand) {
+        if command.bot_policy == BotPolicy::Drop {
+            contributors.retain(|contributor| !contributor.is_bot(command));
+        }
+    }
+
+    ///Merges contributors which are aliases of the same person under the canonical identity
+    fn fold_aliases(
+        contributors: Vec<ContributorDetails>,
+        aliases: &AliasMap,
+    ) -> Vec<ContributorDetails> {
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        let mut folded: Vec<ContributorDetails> = Vec::with_capacity(contributors.len());
+        for contributor in contributors {
+            let identity = contributor.canonical_identity(aliases);
+            match positions.get(&identity) {
+                Some(&position) => {
+                    let folded = &mut folded[position];
+                    folded.contributions = folded
+                        .contributions
+                        .saturating_add(contributor.contributions);
+                    //Merged identity is a bot account only if every alias is one
+                    if folded.kind != contributor.kind {
+                        folded.kind = String::new();
+                    }
+                }
+                None => {
+                    positions.insert(identity.clone(), folded.len());
+                    folded.push(ContributorDetails {
+                        login: Some(identity),
+                        ..contributor
+                    });
+                }
             }
-            None
-        })
+        }
+        folded
     }
 
-    fn calculate_commits_sum(contributors: &[ContributorDetails]) -> u32 {
+    ///Converts contributors into weights used by the detection
+    fn weigh(
+        contributors: &[ContributorDetails],
+        command: &BusFactorQueryCommand,
+    ) -> Vec<AuthorWeight> {
         contributors
             .iter()
-            .fold(0, |prev, next| prev + next.contributions)
+            .map(|contributor| AuthorWeight {
+                identity: contributor.identity(),
+                weight: contributor.contributions,
+                bot: contributor.is_bot(command),
+            })
+            .collect()
     }
 
-    fn create_contrib_url(base_url: &str) -> Result<Url> {
-        Url::parse_with_params(
-            base_url,
-            &[
-                ("sort", "contributions"),
-                ("order", "desc"),
-                ("per_page", "25"),
-            ],
+    fn calculate_weight_sum(authors: &[AuthorWeight]) -> u64 {
+        authors
+            .iter()
+            .fold(0, |prev, next| prev.saturating_add(next.weight))
+    }
+
+    fn create_contrib_url(base_url: &str, command: &BusFactorQueryCommand) -> Result<Url> {
+        let mut params = vec![
+            ("sort", "contributions"),
+            ("order", "desc"),
+            ("per_page", CONTRIBUTORS_PAGE_SIZE),
+        ];
+        if command.anonymous {
+            params.push(("anon", "1"));
+        }
+        Url::parse_with_params(base_url, &params)
+            .map_err(|err| anyhow!(err))
+            .with_context(|| "Could not create URL")
+    }
+
+    ///Contributors built from weekly statistics, `contributions` holding the requested weight
+    pub(crate) async fn fetch_activity(
+        query_result: &RepositoryQueryResult,
+        weight: ContributionWeight,
+        window: Option<&TimeWindow>,
+    ) -> Result<Vec<ContributorDetails>> {
+        Ok(
+            ContributorStatsHandler::fetch_activity(query_result, window)
+                .await?
+                .into_iter()
+                .map(|activity| ContributorDetails {
+                    contributions: weight.measure(
+                        activity.commits,
+                        activity.additions,
+                        activity.deletions,
+                    ),
+                    login: Some(activity.login),
+                    name: None,
+                    email: None,
+                    kind: activity.kind,
+                    other: Default::default(),
+                })
+                .collect(),
         )
-        .map_err(|err| anyhow!(err))
-        .with_context(|| "Could not create URL")
+    }
+
+    ///Follows `Link: rel="next"` headers up to `max_contributor_pages` pages.
+    ///Returns fetched contributors and whether the list was truncated.
+    pub(crate) async fn fetch_all_pages(
+        query_result: &RepositoryQueryResult,
+    ) -> Result<(Vec<ContributorDetails>, bool)> {
+        let command = &query_result.client_details.command;
+        let max_pages = command.max_contributor_pages;
+        let mut contributors = vec![];
+        let mut next_url = Some(Self::create_contrib_url(
+            &query_result.contributor_url,
+            command,
+        )?);
+        let mut fetched_pages = 0;
+        while let Some(full_url) = next_url {
+            if fetched_pages == max_pages {
+                log::warn!(
+                    "Contributor list of {} truncated after {max_pages} pages",
+                    query_result.project_name
+                );
+                return Ok((contributors, true));
+            }
+            let (response, next_page) = Self::fetch_page_of_results(full_url, query_result).await?;
+            contributors.extend(response.contributors);
+            fetched_pages += 1;
+            next_url = next_page;
+        }
+        Ok((contributors, false))
     }
 
     async fn fetch_page_of_results(
+        full_url: Url,
         query_result: &RepositoryQueryResult,
-    ) -> Result<ContributorsResponse> {
-        let full_url = Self::create_contrib_url(&query_result.contributor_url)?;
+    ) -> Result<(ContributorsResponse, Option<Url>)> {
         log::trace!("Targeting {:?}", &full_url);
         let request = query_result.client_details.client.get(full_url);
         log::trace!("{:?}", &request);
-        let response = request.send().await?.json::<ContributorsResponse>().await?;
+        let response = ensure_contributor_data(request.send().await?).await?;
+        let next_page = next_page_url(response.headers());
+        let response = response.json::<ContributorsResponse>().await?;
         log::trace!("{:?}", &response);
-        Ok(response)
+        Ok((response, next_page))
     }
 }
 #[derive(Deserialize, Debug)]
@@ -97,85 +400,433 @@ impl ContributorHandler {
 struct ContributorsResponse {
     contributors: Vec<ContributorDetails>,
 }
+///Either a GitHub account or, with `anon=1`, an anonymous commit author
+///(`type: "Anonymous"` with `name` and `email` instead of `login`).
+///Other providers fill in whichever of `login`, `name` and `email` they know.
 #[derive(Deserialize, Debug)]
-#[allow(dead_code)]
-struct ContributorDetails {
-    login: String,
-    contributions: u32,
+pub struct ContributorDetails {
+    pub login: Option<String>,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    ///Account type, `Bot` marks automation accounts
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    ///Contributions measured in the requested weight
+    pub contributions: u64,
     #[serde(flatten)]
-    other: Other,
+    pub other: Other,
+}
+///Share of work attributed to one author, measured in commits or changed lines
+#[derive(Debug)]
+pub(crate) struct AuthorWeight {
+    pub(crate) identity: String,
+    pub(crate) weight: u64,
+    pub(crate) bot: bool,
+}
+impl ContributorDetails {
+    ///Login of the account, or the git author name (email as a fallback) for anonymous entries
+    fn identity(&self) -> String {
+        self.login
+            .as_ref()
+            .or(self.name.as_ref())
+            .or(self.email.as_ref())
+            .cloned()
+            .unwrap_or_else(|| "anonymous".to_string())
+    }
+    ///Canonical identity from the alias map, matched by login, email or name
+    fn canonical_identity(&self, aliases: &AliasMap) -> String {
+        [&self.login, &self.email, &self.name]
+            .into_iter()
+            .flatten()
+            .find_map(|key| aliases.canonical(key))
+            .map(str::to_string)
+            .unwrap_or_else(|| self.identity())
+    }
+    ///Automation account detected by `type: "Bot"` or `[bot]` suffix,
+    ///unless overridden by user supplied allow/deny lists
+    fn is_bot(&self, command: &BusFactorQueryCommand) -> bool {
+        ContributorHandler::is_bot(&self.identity(), self.kind == "Bot", command)
+    }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::FlagCriterion;
+    fn contributor(login: &str, weight: u64) -> AuthorWeight {
+        AuthorWeight {
+            identity: login.to_string(),
+            weight,
+            bot: false,
+        }
+    }
     #[test]
     fn calculate_bus_factor_simple() {
         let result = ContributorHandler::detect_bus_factor(
-            &[ContributorDetails {
-                login: "luke".to_string(),
-                contributions: 1000,
-                other: Default::default(),
-            }],
+            &[contributor("luke", 1000)],
             "minigun",
-            100,
+            &BusFactorQueryCommand::default(),
         );
-        assert!(result.is_some());
-        assert_eq!(result.unwrap().bus_factor, 100)
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].percentage, 100)
     }
     #[test]
     fn calculate_bus_factor_advance() {
         let result = ContributorHandler::detect_bus_factor(
-            &[
-                ContributorDetails {
-                    login: "luke".to_string(),
-                    contributions: 1000,
-                    other: Default::default(),
-                },
-                ContributorDetails {
-                    login: "kubot".to_string(),
-                    contributions: 500,
-                    other: Default::default(),
-                },
-            ],
+            &[contributor("luke", 1000), contributor("kubot", 500)],
             "minigun",
-            100,
+            &BusFactorQueryCommand::default(),
         );
-        assert!(result.is_none());
+        assert!(result.is_empty());
     }
     #[test]
     fn calculate_bus_factor_almost() {
+        let result = ContributorHandler::detect_bus_factor(
+            &[contributor("luke", 750), contributor("kubot", 250)],
+            "minigun",
+            &BusFactorQueryCommand::default(),
+        );
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].percentage, 75)
+    }
+    #[test]
+    fn calculate_bus_factor_custom_threshold() {
+        let contributors = [contributor("luke", 600), contributor("kubot", 400)];
+        let result = ContributorHandler::detect_bus_factor(
+            &contributors,
+            "minigun",
+            &BusFactorQueryCommand::default(),
+        );
+        assert!(result.is_empty());
+        let result = ContributorHandler::detect_bus_factor(
+            &contributors,
+            "minigun",
+            &BusFactorQueryCommand {
+                threshold: 60,
+                ..Default::default()
+            },
+        );
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].percentage, 60)
+    }
+    #[test]
+    fn calculate_bus_factor_every_dominant() {
         let result = ContributorHandler::detect_bus_factor(
             &[
-                ContributorDetails {
-                    login: "luke".to_string(),
-                    contributions: 750,
-                    other: Default::default(),
-                },
-                ContributorDetails {
-                    login: "kubot".to_string(),
-                    contributions: 250,
-                    other: Default::default(),
-                },
+                contributor("kubot", 300),
+                contributor("han", 100),
+                contributor("luke", 400),
+                contributor("leia", 200),
             ],
             "minigun",
+            &BusFactorQueryCommand {
+                threshold: 20,
+                ..Default::default()
+            },
+        );
+        let ranked: Vec<(&str, u32)> = result
+            .iter()
+            .map(|dominant| (dominant.login.as_str(), dominant.percentage))
+            .collect();
+        assert_eq!(ranked, vec![("luke", 40), ("kubot", 30), ("leia", 20)]);
+    }
+    #[test]
+    fn calculate_bus_factor_number() {
+        let contributors = [
+            contributor("kubot", 200),
+            contributor("luke", 400),
+            contributor("han", 150),
+            contributor("leia", 250),
+        ];
+        let result = ContributorHandler::calculate_bus_factor(
+            &contributors,
+            "minigun",
+            100,
+            &BusFactorQueryCommand::default(),
+        );
+        assert_eq!(result.bus_factor, 2);
+        assert_eq!(result.key_contributors, vec!["luke", "leia"]);
+        assert!(result.dominant_contributors.is_empty());
+        let result = ContributorHandler::calculate_bus_factor(
+            &contributors,
+            "minigun",
             100,
+            &BusFactorQueryCommand {
+                coverage: 90,
+                ..Default::default()
+            },
+        );
+        assert_eq!(result.bus_factor, 4);
+        assert_eq!(
+            result.key_contributors,
+            vec!["luke", "leia", "kubot", "han"]
         );
-        assert!(result.is_some());
-        assert_eq!(result.unwrap().bus_factor, 75)
     }
     #[test]
-    fn calculate_commits_sum() {
-        let result = ContributorHandler::calculate_commits_sum(&[
-            ContributorDetails {
-                login: "luke".to_string(),
-                contributions: 750,
-                other: Default::default(),
+    fn calculate_bus_factor_huge_weights() {
+        let contributors = [
+            contributor("luke", u64::MAX - 1),
+            contributor("leia", u64::MAX / 2),
+            contributor("han", u64::MAX / 4),
+        ];
+        let result = ContributorHandler::calculate_bus_factor(
+            &contributors,
+            "minigun",
+            100,
+            &BusFactorQueryCommand::default(),
+        );
+        assert_eq!(result.bus_factor, 1);
+        assert_eq!(result.key_contributors, vec!["luke"]);
+        let result = ContributorHandler::calculate_bus_factor(
+            &contributors,
+            "minigun",
+            100,
+            &BusFactorQueryCommand {
+                coverage: 100,
+                ..Default::default()
             },
-            ContributorDetails {
-                login: "kubot".to_string(),
-                contributions: 250,
-                other: Default::default(),
+        );
+        assert_eq!(result.bus_factor, 2);
+    }
+    #[test]
+    fn calculate_bus_factor_number_single_author() {
+        let result = ContributorHandler::calculate_bus_factor(
+            &[contributor("luke", 750), contributor("kubot", 250)],
+            "minigun",
+            100,
+            &BusFactorQueryCommand::default(),
+        );
+        assert_eq!(result.bus_factor, 1);
+        assert_eq!(result.key_contributors, vec!["luke"]);
+        assert_eq!(result.dominant_contributors[0].login, "luke");
+    }
+    #[test]
+    fn calculate_bus_factor_anonymous() {
+        let contributors: Vec<ContributorDetails> = serde_json::from_value(serde_json::json!([
+            {"login": "luke", "type": "User", "contributions": 100},
+            {"email": "kubot@example.com", "name": "Kubot", "type": "Anonymous", "contributions": 900}
+        ]))
+        .unwrap();
+        let command = BusFactorQueryCommand::default();
+        let authors = ContributorHandler::weigh(&contributors, &command);
+        assert_eq!(ContributorHandler::calculate_weight_sum(&authors), 1000);
+        let result = ContributorHandler::detect_bus_factor(&authors, "minigun", &command);
+        let result = &result[0];
+        assert_eq!(result.login, "Kubot");
+        assert_eq!(result.percentage, 90);
+    }
+    fn bots() -> Vec<ContributorDetails> {
+        serde_json::from_value(serde_json::json!([
+            {"login": "dependabot[bot]", "type": "User", "contributions": 800},
+            {"login": "ci-runner", "type": "Bot", "contributions": 50},
+            {"login": "luke", "type": "User", "contributions": 100},
+            {"login": "kubot", "type": "User", "contributions": 50}
+        ]))
+        .unwrap()
+    }
+    fn identities(contributors: &[ContributorDetails]) -> Vec<String> {
+        contributors
+            .iter()
+            .map(ContributorDetails::identity)
+            .collect()
+    }
+    #[test]
+    fn filter_bots_drop() {
+        let mut contributors = bots();
+        let command = BusFactorQueryCommand::default();
+        ContributorHandler::filter_bots(&mut contributors, &command);
+        assert_eq!(identities(&contributors), vec!["luke", "kubot"]);
+        let authors = ContributorHandler::weigh(&contributors, &command);
+        let result = ContributorHandler::calculate_bus_factor(&authors, "minigun", 100, &command);
+        assert_eq!(result.key_contributors, vec!["luke"]);
+        assert!(result.dominant_contributors.is_empty());
+    }
+    #[test]
+    fn filter_bots_allow_deny() {
+        let mut contributors = bots();
+        let command = BusFactorQueryCommand {
+            allow_logins: vec!["ci-runner".to_string()],
+            deny_logins: vec!["kubot".to_string()],
+            ..Default::default()
+        };
+        ContributorHandler::filter_bots(&mut contributors, &command);
+        assert_eq!(identities(&contributors), vec!["ci-runner", "luke"]);
+    }
+    #[test]
+    fn filter_bots_exclude_from_dominant() {
+        let mut contributors = bots();
+        let command = BusFactorQueryCommand {
+            bot_policy: BotPolicy::ExcludeFromDominant,
+            threshold: 10,
+            ..Default::default()
+        };
+        ContributorHandler::filter_bots(&mut contributors, &command);
+        assert_eq!(contributors.len(), 4);
+        let authors = ContributorHandler::weigh(&contributors, &command);
+        let result = ContributorHandler::detect_bus_factor(&authors, "minigun", &command);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].login, "luke");
+        assert_eq!(result[0].percentage, 10);
+    }
+    #[test]
+    fn fold_aliases() {
+        let contributors: Vec<ContributorDetails> = serde_json::from_value(serde_json::json!([
+            {"login": "luke", "type": "User", "contributions": 400},
+            {"login": "kubot", "type": "User", "contributions": 200},
+            {"login": "luke-work", "type": "User", "contributions": 200},
+            {"email": "luke@rebels.org", "name": "Luke", "type": "Anonymous", "contributions": 100}
+        ]))
+        .unwrap();
+        let aliases =
+            AliasMap::from_file(std::path::Path::new("tests/fixtures/aliases.mailmap")).unwrap();
+        let contributors = ContributorHandler::fold_aliases(contributors, &aliases);
+        let command = BusFactorQueryCommand::default();
+        let authors = ContributorHandler::weigh(&contributors, &command);
+        let result = ContributorHandler::calculate_bus_factor(&authors, "minigun", 100, &command);
+        assert_eq!(result.key_contributors, vec!["Luke Skywalker"]);
+        let dominant = &result.dominant_contributors[0];
+        assert_eq!(dominant.login, "Luke Skywalker");
+        assert_eq!(dominant.contributions, 700);
+    }
+    #[test]
+    fn fold_aliases_before_bot_policy() {
+        let contributors: Vec<ContributorDetails> = serde_json::from_value(serde_json::json!([
+            {"login": "luke", "type": "User", "contributions": 100},
+            {"login": "luke-ci[bot]", "type": "Bot", "contributions": 300},
+            {"login": "builder", "type": "User", "contributions": 200},
+            {"login": "kubot", "type": "User", "contributions": 50}
+        ]))
+        .unwrap();
+        let command = BusFactorQueryCommand {
+            aliases: AliasMap::from_file(std::path::Path::new("tests/fixtures/aliases.toml"))
+                .unwrap(),
+            deny_logins: vec!["release".to_string()],
+            ..Default::default()
+        };
+        let contributors = ContributorHandler::prepare(contributors, &command);
+        assert_eq!(identities(&contributors), vec!["luke", "kubot"]);
+        assert_eq!(contributors[0].contributions, 400);
+    }
+    #[test]
+    fn contribution_weight_measure() {
+        let activity = [(10, 1000, 500), (90, 100, 0)];
+        let expected = [
+            (ContributionWeight::Commits, [10, 90]),
+            (ContributionWeight::Additions, [1000, 100]),
+            (ContributionWeight::Churn, [1500, 100]),
+        ];
+        for (weight, expected) in expected {
+            let weights: Vec<u64> = activity
+                .iter()
+                .map(|&(commits, additions, deletions)| {
+                    weight.measure(commits, additions, deletions)
+                })
+                .collect();
+            assert_eq!(weights, expected);
+        }
+    }
+    fn assert_close(value: f64, expected: f64) {
+        assert!(
+            (value - expected).abs() < 0.0001,
+            "{value} is not close to {expected}"
+        );
+    }
+    #[test]
+    fn concentration_single_contributor() {
+        let metrics = ContributorHandler::concentration([1000, 0].into_iter());
+        assert_eq!(metrics.contributors, 1);
+        assert_close(metrics.gini, 0.0);
+        assert_close(metrics.hhi, 1.0);
+        assert_close(metrics.entropy, 0.0);
+        assert_close(metrics.top3_share, 1.0);
+        assert_close(metrics.score(FlagCriterion::Entropy).unwrap(), 100.0);
+    }
+    #[test]
+    fn concentration_equal_contributors() {
+        let metrics = ContributorHandler::concentration([25, 25, 25, 25].into_iter());
+        assert_close(metrics.gini, 0.0);
+        assert_close(metrics.hhi, 0.25);
+        assert_close(metrics.entropy, 2.0);
+        assert_close(metrics.top3_share, 0.75);
+        assert_close(metrics.score(FlagCriterion::Entropy).unwrap(), 0.0);
+        assert_close(metrics.score(FlagCriterion::Top3).unwrap(), 75.0);
+    }
+    #[test]
+    fn concentration_unequal_contributors() {
+        let metrics = ContributorHandler::concentration([10, 10, 80].into_iter());
+        assert_close(metrics.gini, 0.4667);
+        assert_close(metrics.hhi, 0.66);
+        assert_close(metrics.entropy, 0.9219);
+        assert_close(metrics.top3_share, 1.0);
+        let empty = ContributorHandler::concentration(std::iter::empty());
+        assert_eq!(empty.contributors, 0);
+        assert_close(empty.score(FlagCriterion::Gini).unwrap(), 0.0);
+    }
+    #[test]
+    fn flag_by_criterion() {
+        let authors = [
+            contributor("luke", 80),
+            contributor("kubot", 10),
+            contributor("han", 10),
+        ];
+        let result = ContributorHandler::calculate_bus_factor(
+            &authors,
+            "minigun",
+            100,
+            &BusFactorQueryCommand::default(),
+        );
+        assert!(result.flagged);
+        let result = ContributorHandler::calculate_bus_factor(
+            &authors,
+            "minigun",
+            100,
+            &BusFactorQueryCommand {
+                criterion: FlagCriterion::Gini,
+                threshold: 50,
+                ..Default::default()
+            },
+        );
+        assert!(!result.flagged);
+        let result = ContributorHandler::calculate_bus_factor(
+            &authors,
+            "minigun",
+            100,
+            &BusFactorQueryCommand {
+                criterion: FlagCriterion::Hhi,
+                threshold: 50,
+                ..Default::default()
             },
+        );
+        assert!(result.flagged);
+    }
+    #[test]
+    fn calculate_bus_factor_zero_weight() {
+        let authors = [contributor("luke", 0), contributor("kubot", 0)];
+        let result = ContributorHandler::calculate_bus_factor(
+            &authors,
+            "minigun",
+            100,
+            &BusFactorQueryCommand::default(),
+        );
+        assert_eq!(result.bus_factor, 0);
+        assert!(result.dominant_contributors.is_empty());
+        assert!(!result.flagged);
+    }
+    #[test]
+    fn calculate_weight_sum_huge() {
+        let authors = [contributor("luke", u64::MAX), contributor("kubot", 1)];
+        assert_eq!(ContributorHandler::calculate_weight_sum(&authors), u64::MAX);
+        let result = ContributorHandler::detect_bus_factor(
+            &authors,
+            "minigun",
+            &BusFactorQueryCommand::default(),
+        );
+        assert_eq!(result[0].percentage, 100);
+    }
+    #[test]
+    fn calculate_weight_sum() {
+        let result = ContributorHandler::calculate_weight_sum(&[
+            contributor("luke", 750),
+            contributor("kubot", 250),
         ]);
         assert_eq!(result, 1000)
     }
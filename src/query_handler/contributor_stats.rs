@@ -0,0 +1,270 @@
+use crate::query_handler::{ensure_contributor_data, NoDataReason, Other, RepositoryQueryResult};
+use anyhow::{anyhow, bail, Result};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+const STATS_POLL_ATTEMPTS: u32 = 6;
+const STATS_POLL_INITIAL_DELAY: Duration = Duration::from_secs(1);
+
+///Recent period of activity, e.g. `90d`, `8w`, `12m` or `2y`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimeWindow {
+    label: String,
+    duration: Duration,
+}
+
+impl TimeWindow {
+    ///Unix timestamp of the window start, counted back from now
+    pub(crate) fn start(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .saturating_sub(self.duration)
+            .as_secs()
+    }
+    ///Window start as `YYYY-MM-DDTHH:MM:SSZ`, accepted by `since` parameters of the commit APIs
+    pub(crate) fn start_iso(&self) -> String {
+        iso_timestamp(self.start())
+    }
+}
+
+impl FromStr for TimeWindow {
+    type Err = anyhow::Error;
+
+    fn from_str(window: &str) -> Result<Self> {
+        let window = window.trim();
+        let invalid = || anyhow!("Invalid time window {window}, expected e.g. 90d, 8w, 12m, 2y");
+        let (unit_start, unit) = window.char_indices().last().ok_or_else(invalid)?;
+        let amount: u64 = window[..unit_start].parse().map_err(|_| invalid())?;
+        let days = match unit {
+            'd' => 1,
+            'w' => 7,
+            'm' => 30,
+            'y' => 365,
+            _ => bail!("Invalid time window unit in {window}, expected one of d, w, m, y"),
+        };
+        if amount == 0 {
+            bail!("Time window {window} has to be longer than zero");
+        }
+        let seconds = amount
+            .checked_mul(days * SECONDS_PER_DAY)
+            .ok_or_else(|| anyhow!("Time window {window} is too long"))?;
+        Ok(TimeWindow {
+            label: window.to_string(),
+            duration: Duration::from_secs(seconds),
+        })
+    }
+}
+
+///`YYYY-MM-DDTHH:MM:SSZ` of a Unix timestamp, as expected by GitHub
+fn iso_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let seconds = timestamp % 86_400;
+    //Civil date of days since 1970-01-01, after Howard Hinnant's `civil_from_days`
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+///Weekly activity of contributors from `/repos/{owner}/{repo}/stats/contributors`.
+///GitHub computes these statistics lazily and answers `202 Accepted` until they are ready.
+pub(crate) struct ContributorStatsHandler;
+
+impl ContributorStatsHandler {
+    ///Activity of every author summed over weeks starting within the window,
+    ///or over the whole history when no window is given.
+    pub(crate) async fn fetch_activity(
+        data: &RepositoryQueryResult,
+        window: Option<&TimeWindow>,
+    ) -> Result<Vec<AuthorActivity>> {
+        let stats = Self::fetch_stats(data).await?;
+        let since = window.map_or(0, TimeWindow::start);
+        Ok(Self::summarize(stats, since))
+    }
+
+    fn summarize(stats: Vec<ContributorStats>, since: u64) -> Vec<AuthorActivity> {
+        stats
+            .into_iter()
+            .filter_map(|stats| {
+                let author = stats.author?;
+                let mut activity = AuthorActivity {
+                    login: author.login,
+                    kind: author.kind,
+                    commits: 0,
+                    additions: 0,
+                    deletions: 0,
+                };
+                for week in stats.weeks.iter().filter(|week| week.w >= since) {
+                    activity.commits += week.c;
+                    activity.additions += week.a;
+                    activity.deletions += week.d;
+                }
+                (activity.commits > 0).then_some(activity)
+            })
+            .collect()
+    }
+
+    async fn fetch_stats(data: &RepositoryQueryResult) -> Result<Vec<ContributorStats>> {
+        if data.repository_url.is_empty() {
+            bail!("Missing repository URL for {}", data.project_name);
+        }
+        let full_url = format!("{}/stats/contributors", data.repository_url);
+        let mut delay = STATS_POLL_INITIAL_DELAY;
+        for attempt in 1..=STATS_POLL_ATTEMPTS {
+            log::trace!("Targeting {:?}", &full_url);
+            let response = data.client_details.client.get(&full_url).send().await?;
+            if response.status() != StatusCode::ACCEPTED {
+                let response = ensure_contributor_data(response)
+                    .await?
+                    .json::<Vec<ContributorStats>>()
+                    .await?;
+                log::trace!("{:?}", &response);
+                return Ok(response);
+            }
+            log::debug!(
+                "Statistics of {} are being computed, attempt {attempt}/{STATS_POLL_ATTEMPTS}",
+                data.project_name
+            );
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+        bail!(NoDataReason::StatisticsPending)
+    }
+}
+
+///Activity of one author within the requested window
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct AuthorActivity {
+    pub(crate) login: String,
+    pub(crate) kind: String,
+    pub(crate) commits: u64,
+    pub(crate) additions: u64,
+    pub(crate) deletions: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct ContributorStats {
+    author: Option<StatsAuthor>,
+    weeks: Vec<WeeklyActivity>,
+}
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)]
+struct StatsAuthor {
+    login: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(flatten)]
+    other: Other,
+}
+///`w` - start of the week (unix timestamp), `a` - additions, `d` - deletions, `c` - commits
+#[derive(Deserialize, Debug)]
+struct WeeklyActivity {
+    w: u64,
+    a: u64,
+    d: u64,
+    c: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse_time_window() {
+        let window: TimeWindow = "12m".parse().unwrap();
+        assert_eq!(window.duration, Duration::from_secs(360 * SECONDS_PER_DAY));
+        assert_eq!(window.to_string(), "12m");
+        let window: TimeWindow = "2w".parse().unwrap();
+        assert_eq!(window.duration, Duration::from_secs(14 * SECONDS_PER_DAY));
+        assert!("12".parse::<TimeWindow>().is_err());
+        assert!("m".parse::<TimeWindow>().is_err());
+        assert!("0d".parse::<TimeWindow>().is_err());
+        assert!("5h".parse::<TimeWindow>().is_err());
+        assert!("5é".parse::<TimeWindow>().is_err());
+        assert!("99999999999999y".parse::<TimeWindow>().is_err());
+    }
+    #[test]
+    fn iso_timestamps() {
+        assert_eq!(iso_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(iso_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(iso_timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+    #[test]
+    fn summarize_window() {
+        let stats: Vec<ContributorStats> = serde_json::from_value(serde_json::json!([
+            {
+                "author": {"login": "luke", "type": "User"},
+                "total": 11,
+                "weeks": [
+                    {"w": 100, "a": 1000, "d": 10, "c": 10},
+                    {"w": 200, "a": 5, "d": 1, "c": 1}
+                ]
+            },
+            {
+                "author": {"login": "kubot", "type": "User"},
+                "total": 3,
+                "weeks": [
+                    {"w": 100, "a": 0, "d": 0, "c": 0},
+                    {"w": 200, "a": 30, "d": 20, "c": 3}
+                ]
+            },
+            {
+                "author": {"login": "han", "type": "User"},
+                "total": 5,
+                "weeks": [{"w": 100, "a": 50, "d": 0, "c": 5}]
+            },
+            {
+                "author": null,
+                "total": 1,
+                "weeks": [{"w": 200, "a": 1, "d": 1, "c": 1}]
+            }
+        ]))
+        .unwrap();
+        let activity = ContributorStatsHandler::summarize(stats, 150);
+        assert_eq!(
+            activity,
+            vec![
+                AuthorActivity {
+                    login: "luke".to_string(),
+                    kind: "User".to_string(),
+                    commits: 1,
+                    additions: 5,
+                    deletions: 1,
+                },
+                AuthorActivity {
+                    login: "kubot".to_string(),
+                    kind: "User".to_string(),
+                    commits: 3,
+                    additions: 30,
+                    deletions: 20,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use crate::query_handler::{
+    ensure_contributor_data, ensure_repository, next_page_url, repository_api_url,
+    HttpClientDetails, Other, RepositoryQueryResult,
+};
+use crate::ContributionWeight;
+use anyhow::{anyhow, Context, Result};
+use itertools::Itertools;
+use reqwest::Url;
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+
+///Default `MAX_RESPONSE_ITEMS` of Gitea and Forgejo, larger pages are cut to it
+const GITEA_PAGE_SIZE: u32 = 50;
+
+///Repositories and commit authors from the Gitea REST API v1, also served by Forgejo.
+///Gitea has no contributors endpoint, so contributions are counted from the commit listing
+///of the default branch, `max_commits` limiting the number of counted commits.
+pub(crate) struct GiteaHandler;
+
+///One text search, or one exact topic search when `topic` is set
+struct RepositorySearch<'a> {
+    term: &'a str,
+    topic: bool,
+}
+
+impl GiteaHandler {
+    ///Most starred repositories of every language, searched by topics or `query` terms.
+    ///The search API does not filter by language, so the primary language of results is compared
+    ///and every search stops after `max_search_pages` pages or once `project_count` matched.
+    pub(crate) async fn search_repositories(
+        client_details: Arc<HttpClientDetails>,
+        tx: Sender<RepositoryQueryResult>,
+    ) -> Result<()> {
+        let command = &client_details.command;
+        let query = command.query.join(" ");
+        let topics: Vec<&str> = command
+            .topics
+            .iter()
+            .map(|topic| topic.trim())
+            .filter(|topic| !topic.is_empty())
+            .collect();
+        let searches: Vec<RepositorySearch> = if topics.is_empty() {
+            vec![RepositorySearch {
+                term: query.trim(),
+                topic: false,
+            }]
+        } else {
+            topics
+                .into_iter()
+                .map(|term| RepositorySearch { term, topic: true })
+                .collect()
+        };
+        let mut seen = HashSet::new();
+        for language in command.search_languages() {
+            let mut remaining = command.project_count as usize;
+            for search in &searches {
+                let mut next_page = Some(create_search_url(&client_details, search)?);
+                let mut pages = 0;
+                while let (Some(full_url), true) = (next_page, remaining > 0) {
+                    if pages == command.max_search_pages {
+                        log::warn!(
+                            "Stopping search {:?} after {pages} pages, {remaining} {language} repositories missing",
+                            search.term
+                        );
+                        break;
+                    }
+                    pages += 1;
+                    log::trace!("Targeting {:?}", &full_url);
+                    let response = client_details
+                        .client
+                        .get(full_url)
+                        .send()
+                        .await?
+                        .error_for_status()?;
+                    next_page = next_page_url(response.headers());
+                    let response = response.json::<SearchResponse>().await?;
+                    log::trace!("{:?}", &response);
+                    if response.data.is_empty() {
+                        break;
+                    }
+                    for repository in response.data {
+                        if remaining == 0 || !repository.has_language(&language) {
+                            continue;
+                        }
+                        if !seen.insert(repository.full_name.clone()) {
+                            log::debug!(
+                                "Skipping {}, already found by another search",
+                                repository.full_name
+                            );
+                            continue;
+                        }
+                        remaining -= 1;
+                        tx.send(repository.into_query_result(&language, client_details.clone()))
+                            .await
+                            .expect("Could not send message to contributor query handler");
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    ///Looks up an `owner/name` repository via `/repos/{owner}/{repo}`, `None` when it does not exist
+    pub(crate) async fn fetch_repository(
+        repository: &str,
+        client_details: Arc<HttpClientDetails>,
+    ) -> Result<Option<RepositoryQueryResult>> {
+        let full_url = repository_api_url(&client_details.command.gitea_url, repository)?;
+        log::trace!("Targeting {:?}", &full_url);
+        let response = client_details.client.get(full_url).send().await?;
+        let Some(response) = ensure_repository(response)? else {
+            log::warn!("Repository {repository} not found");
+            return Ok(None);
+        };
+        let details = response
+            .error_for_status()?
+            .json::<GiteaRepository>()
+            .await?;
+        log::trace!("{:?}", &details);
+        Ok(Some(details.into_query_result("", client_details)))
+    }
+
+    ///Authors of commits on the default branch with their summed work, most commits first.
+    ///Returns counted authors and whether the commit listing was truncated.
+    pub(crate) async fn count_authors(
+        data: &RepositoryQueryResult,
+    ) -> Result<(Vec<GiteaAuthor>, bool)> {
+        let command = &data.client_details.command;
+        let max_pages = command.max_commits.div_ceil(GITEA_PAGE_SIZE);
+        let page_size = GITEA_PAGE_SIZE.to_string();
+        //Line statistics are expensive to compute, they are only requested when weighed by
+        let stat = command.weight != ContributionWeight::Commits;
+        let mut commits = vec![];
+        let mut next_page = Some(
+            Url::parse_with_params(
+                &data.contributor_url,
+                &[
+                    ("sha", data.default_branch.as_str()),
+                    ("limit", page_size.as_str()),
+                    ("stat", if stat { "true" } else { "false" }),
+                    ("verification", "false"),
+                    ("files", "false"),
+                ],
+            )
+            .map_err(|err| anyhow!(err))
+            .context("Could not create URL")?,
+        );
+        let mut fetched_pages = 0;
+        while let Some(full_url) = next_page {
+            if fetched_pages == max_pages {
+                log::warn!(
+                    "Commit history of {} truncated after {max_pages} pages",
+                    data.project_name
+                );
+                return Ok((Self::summarize(commits), true));
+            }
+            log::trace!("Targeting {:?}", &full_url);
+            let response = data.client_details.client.get(full_url).send().await?;
+            let response = ensure_contributor_data(response).await?;
+            next_page = next_page_url(response.headers());
+            let page = response.json::<Vec<CommitDetails>>().await?;
+            log::trace!("{:?}", &page);
+            if page.is_empty() {
+                break;
+            }
+            commits.extend(page);
+            fetched_pages += 1;
+        }
+        Ok((Self::summarize(commits), false))
+    }
+
+    ///Commits grouped by the linked account, or by the git author email for unlinked commits
+    fn summarize(commits: Vec<CommitDetails>) -> Vec<GiteaAuthor> {
+        let mut authors: HashMap<String, GiteaAuthor> = HashMap::new();
+        for commit in commits {
+            let login = commit
+                .author
+                .map(|author| author.login)
+                .filter(|login| !login.is_empty());
+            let signature = commit.commit.author;
+            let key = login.clone().unwrap_or_else(|| signature.email.clone());
+            let author = authors.entry(key).or_insert_with(|| GiteaAuthor {
+                login,
+                name: signature.name,
+                email: signature.email,
+                commits: 0,
+                additions: 0,
+                deletions: 0,
+            });
+            author.commits += 1;
+            if let Some(stats) = commit.stats {
+                author.additions += stats.additions;
+                author.deletions += stats.deletions;
+            }
+        }
+        authors
+            .into_values()
+            .sorted_by_key(|author| (Reverse(author.commits), author.email.clone()))
+            .collect()
+    }
+}
+
+///Author of commits, `login` set when the commits are linked to an account of the instance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GiteaAuthor {
+    pub(crate) login: Option<String>,
+    pub(crate) name: String,
+    pub(crate) email: String,
+    pub(crate) commits: u64,
+    pub(crate) additions: u64,
+    pub(crate) deletions: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct SearchResponse {
+    #[serde(default)]
+    data: Vec<GiteaRepository>,
+}
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)]
+struct GiteaRepository {
+    full_name: String,
+    #[serde(default)]
+    stars_count: u32,
+    #[serde(default)]
+    default_branch: String,
+    #[serde(default)]
+    language: String,
+    #[serde(flatten, skip)]
+    other: Other,
+}
+impl GiteaRepository {
+    ///Any repository matches when no language is requested
+    fn has_language(&self, language: &str) -> bool {
+        language.is_empty() || self.language.eq_ignore_ascii_case(language)
+    }
+    fn into_query_result(
+        self,
+        language: &str,
+        client_details: Arc<HttpClientDetails>,
+    ) -> RepositoryQueryResult {
+        let repository_url = format!(
+            "{}/repos/{}",
+            client_details.command.gitea_url, self.full_name
+        );
+        RepositoryQueryResult {
+            stargazers: self.stars_count,
+            contributor_url: format!("{repository_url}/commits"),
+            repository_url,
+            default_branch: if self.default_branch.is_empty() {
+                "HEAD".to_string()
+            } else {
+                self.default_branch
+            },
+            project_name: self.full_name,
+            language: language.to_string(),
+            dependencies: vec![],
+            client_details,
+        }
+    }
+}
+#[derive(Deserialize, Debug)]
+struct CommitDetails {
+    commit: CommitInfo,
+    ///Account the commit is linked to, missing for unknown emails
+    author: Option<CommitAccount>,
+    stats: Option<CommitStats>,
+}
+#[derive(Deserialize, Debug)]
+struct CommitInfo {
+    author: CommitSignature,
+}
+#[derive(Deserialize, Debug)]
+struct CommitSignature {
+    name: String,
+    email: String,
+}
+#[derive(Deserialize, Debug)]
+struct CommitAccount {
+    login: String,
+}
+#[derive(Deserialize, Debug)]
+struct CommitStats {
+    additions: u64,
+    deletions: u64,
+}
+
+///`/repos/search` ordered by stars
+fn create_search_url(client_details: &HttpClientDetails, search: &RepositorySearch) -> Result<Url> {
+    let page_size = GITEA_PAGE_SIZE.to_string();
+    let mut params = vec![
+        ("sort", "stars"),
+        ("order", "desc"),
+        ("limit", page_size.as_str()),
+    ];
+    if !search.term.is_empty() {
+        params.push(("q", search.term));
+    }
+    if search.topic {
+        params.push(("topic", "true"));
+    }
+    Url::parse_with_params(
+        &format!("{}/repos/search", client_details.command.gitea_url),
+        &params,
+    )
+    .map_err(|err| anyhow!(err))
+    .context("Could not create URL")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn summarize_commit_authors() {
+        let commits: Vec<CommitDetails> = serde_json::from_str(
+            r#"[
+                {"commit": {"author": {"name": "Luke", "email": "luke@rebels.org"}}, "author": {"login": "luke"}, "stats": {"total": 12, "additions": 10, "deletions": 2}},
+                {"commit": {"author": {"name": "Luke S.", "email": "luke@jedi.org"}}, "author": {"login": "luke"}, "stats": {"total": 1, "additions": 1, "deletions": 0}},
+                {"commit": {"author": {"name": "Leia", "email": "leia@rebels.org"}}, "author": null}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            GiteaHandler::summarize(commits),
+            vec![
+                GiteaAuthor {
+                    login: Some("luke".to_string()),
+                    name: "Luke".to_string(),
+                    email: "luke@rebels.org".to_string(),
+                    commits: 2,
+                    additions: 11,
+                    deletions: 2,
+                },
+                GiteaAuthor {
+                    login: None,
+                    name: "Leia".to_string(),
+                    email: "leia@rebels.org".to_string(),
+                    commits: 1,
+                    additions: 0,
+                    deletions: 0,
+                },
+            ]
+        );
+    }
+    #[test]
+    fn repository_language_match() {
+        let repository: GiteaRepository = serde_json::from_str(
+            r#"{"full_name": "forgejo/runner", "stars_count": 10, "language": "Go"}"#,
+        )
+        .unwrap();
+        assert!(repository.has_language("go"));
+        assert!(repository.has_language(""));
+        assert!(!repository.has_language("Rust"));
+    }
+}
@@ -0,0 +1,268 @@
+use crate::query_handler::{
+    ensure_contributor_data, ensure_repository, next_page_url, HttpClientDetails, Other,
+    RepositoryQueryResult,
+};
+use anyhow::{anyhow, Context, Result};
+use reqwest::Url;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+
+///Largest page size accepted by the GitLab API
+const GITLAB_PAGE_SIZE: u32 = 100;
+const CONTRIBUTORS_PAGE_SIZE: &str = "100";
+
+///Projects and contributors from the GitLab REST API v4 (`/projects`, `/repository/contributors`).
+///Pages are followed through `Link: rel="next"` headers, which carry the cursor of keyset pagination.
+///GitLab offers keyset pagination for id ordering only, so the star ordered search pages by offset.
+pub(crate) struct GitLabHandler;
+
+impl GitLabHandler {
+    ///Most starred projects of every language, or of the requested topics only when no language is given
+    pub(crate) async fn search_projects(
+        client_details: Arc<HttpClientDetails>,
+        tx: Sender<RepositoryQueryResult>,
+    ) -> Result<()> {
+        let command = &client_details.command;
+        let mut seen = HashSet::new();
+        for language in command.search_languages() {
+            let mut remaining = command.project_count as usize;
+            let mut next_page = Some(create_search_url(&client_details, &language)?);
+            while let (Some(full_url), true) = (next_page, remaining > 0) {
+                log::trace!("Targeting {:?}", &full_url);
+                let response = client_details
+                    .client
+                    .get(full_url)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                next_page = next_page_url(response.headers());
+                let projects = response.json::<Vec<GitLabProject>>().await?;
+                log::trace!("{:?}", &projects);
+                if projects.is_empty() {
+                    break;
+                }
+                for project in projects.into_iter().take(remaining) {
+                    remaining -= 1;
+                    if !seen.insert(project.id) {
+                        log::debug!(
+                            "Skipping {}, already found by another search",
+                            project.path_with_namespace
+                        );
+                        continue;
+                    }
+                    tx.send(project.into_query_result(&language, client_details.clone()))
+                        .await
+                        .expect("Could not send message to contributor query handler");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    ///Looks up a `group/name` project via `/projects/{path}`, `None` when it does not exist
+    pub(crate) async fn fetch_project(
+        repository: &str,
+        client_details: Arc<HttpClientDetails>,
+    ) -> Result<Option<RepositoryQueryResult>> {
+        let full_url = create_project_url(&client_details, repository)?;
+        log::trace!("Targeting {:?}", &full_url);
+        let response = client_details.client.get(full_url).send().await?;
+        let Some(response) = ensure_repository(response)? else {
+            log::warn!("Repository {repository} not found");
+            return Ok(None);
+        };
+        let project = response.error_for_status()?.json::<GitLabProject>().await?;
+        log::trace!("{:?}", &project);
+        Ok(Some(project.into_query_result("", client_details)))
+    }
+
+    ///Contributors by commit count, up to `max_contributor_pages` pages.
+    ///Returns fetched contributors and whether the list was truncated.
+    pub(crate) async fn fetch_contributors(
+        data: &RepositoryQueryResult,
+    ) -> Result<(Vec<GitLabContributor>, bool)> {
+        let max_pages = data.client_details.command.max_contributor_pages;
+        let mut contributors = vec![];
+        let mut next_page = Some(
+            Url::parse_with_params(
+                &data.contributor_url,
+                &[
+                    ("order_by", "commits"),
+                    ("sort", "desc"),
+                    ("per_page", CONTRIBUTORS_PAGE_SIZE),
+                ],
+            )
+            .map_err(|err| anyhow!(err))
+            .context("Could not create URL")?,
+        );
+        let mut fetched_pages = 0;
+        while let Some(full_url) = next_page {
+            if fetched_pages == max_pages {
+                log::warn!(
+                    "Contributor list of {} truncated after {max_pages} pages",
+                    data.project_name
+                );
+                return Ok((contributors, true));
+            }
+            log::trace!("Targeting {:?}", &full_url);
+            let response = data.client_details.client.get(full_url).send().await?;
+            let response = ensure_contributor_data(response).await?;
+            next_page = next_page_url(response.headers());
+            let page = response.json::<Vec<GitLabContributor>>().await?;
+            log::trace!("{:?}", &page);
+            contributors.extend(page);
+            fetched_pages += 1;
+        }
+        Ok((contributors, false))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)]
+struct GitLabProject {
+    id: u64,
+    path_with_namespace: String,
+    #[serde(default)]
+    star_count: u32,
+    default_branch: Option<String>,
+    #[serde(flatten, skip)]
+    other: Other,
+}
+impl GitLabProject {
+    fn into_query_result(
+        self,
+        language: &str,
+        client_details: Arc<HttpClientDetails>,
+    ) -> RepositoryQueryResult {
+        let repository_url = format!(
+            "{}/projects/{}",
+            client_details.command.gitlab_url.trim_end_matches('/'),
+            self.id
+        );
+        RepositoryQueryResult {
+            stargazers: self.star_count,
+            contributor_url: format!("{repository_url}/repository/contributors"),
+            repository_url,
+            default_branch: self.default_branch.unwrap_or_else(|| "HEAD".to_string()),
+            project_name: self.path_with_namespace,
+            language: language.to_string(),
+            dependencies: vec![],
+            client_details,
+        }
+    }
+}
+///Commit author aggregated by GitLab, identified by name and email only
+#[derive(Deserialize, Debug)]
+pub(crate) struct GitLabContributor {
+    pub(crate) name: String,
+    pub(crate) email: String,
+    pub(crate) commits: u64,
+    #[serde(default)]
+    pub(crate) additions: u64,
+    #[serde(default)]
+    pub(crate) deletions: u64,
+}
+
+///`/projects` ordered by stars, filtered by language, topics and free text built from `query`
+fn create_search_url(client_details: &HttpClientDetails, language: &str) -> Result<Url> {
+    let command = &client_details.command;
+    let page_size = command.project_count.min(GITLAB_PAGE_SIZE).to_string();
+    let mut params = vec![
+        ("order_by", "star_count".to_string()),
+        ("sort", "desc".to_string()),
+        ("per_page", page_size),
+    ];
+    if !language.is_empty() {
+        params.push(("with_programming_language", language.to_string()));
+    }
+    let topics: Vec<&str> = command
+        .topics
+        .iter()
+        .map(|topic| topic.trim())
+        .filter(|topic| !topic.is_empty())
+        .collect();
+    if !topics.is_empty() {
+        params.push(("topic", topics.join(",")));
+    }
+    let search = command.query.join(" ");
+    if !search.trim().is_empty() {
+        params.push(("search", search.trim().to_string()));
+    }
+    Url::parse_with_params(
+        &format!("{}/projects", command.gitlab_url.trim_end_matches('/')),
+        &params,
+    )
+    .map_err(|err| anyhow!(err))
+    .context("Could not create URL")
+}
+
+///`/projects/{path}` with the namespaced path URL-encoded, e.g. `/projects/gitlab-org%2Fgitlab`
+fn create_project_url(client_details: &HttpClientDetails, repository: &str) -> Result<Url> {
+    let api_url = &client_details.command.gitlab_url;
+    let mut url = Url::parse(&format!("{}/projects", api_url.trim_end_matches('/')))
+        .map_err(|err| anyhow!(err))
+        .context("Could not create URL")?;
+    //A single segment, so that `/`, `?` or `#` of the path are escaped as well
+    url.path_segments_mut()
+        .map_err(|_| anyhow!("Could not create URL from {api_url}"))?
+        .push(repository);
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BusFactorQueryCommand, Provider};
+    fn client_details(command: BusFactorQueryCommand) -> HttpClientDetails {
+        HttpClientDetails {
+            client: reqwest::Client::new(),
+            command: BusFactorQueryCommand {
+                provider: Provider::GitLab,
+                ..command
+            },
+        }
+    }
+    #[test]
+    fn search_url_filters() {
+        let url = create_search_url(
+            &client_details(BusFactorQueryCommand {
+                topics: vec!["cli".to_string(), " ".to_string(), "devops".to_string()],
+                query: vec!["runner".to_string()],
+                project_count: 250,
+                ..Default::default()
+            }),
+            "Go",
+        )
+        .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://gitlab.com/api/v4/projects?order_by=star_count&sort=desc&per_page=100&with_programming_language=Go&topic=cli%2Cdevops&search=runner"
+        );
+    }
+    #[test]
+    fn project_url_encodes_namespace() {
+        let url = create_project_url(
+            &client_details(BusFactorQueryCommand {
+                gitlab_url: "https://git.example.com/api/v4/".to_string(),
+                ..Default::default()
+            }),
+            "platform/tools/deployer",
+        )
+        .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://git.example.com/api/v4/projects/platform%2Ftools%2Fdeployer"
+        );
+        let url = create_project_url(
+            &client_details(BusFactorQueryCommand::default()),
+            "platform/deploy er?private=true#main%",
+        )
+        .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://gitlab.com/api/v4/projects/platform%2Fdeploy%20er%3Fprivate=true%23main%25"
+        );
+    }
+}
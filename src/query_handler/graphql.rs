@@ -0,0 +1,767 @@
+use crate::query_handler::bus_factor::ContributorDetails;
+use crate::query_handler::repository::{
+    api_url, create_search_query, search_qualifiers, StarRange,
+};
+use crate::query_handler::{
+    now, rate_limit_delay, HttpClientDetails, NoDataReason, RepositoryQueryResult,
+};
+use crate::{BusFactorQueryCommand, ContributionWeight, OwnerScan, TimeWindow};
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+
+///Repositories per search or owner page, halved whenever GitHub rejects a query as too expensive
+const REPOSITORY_BATCH: u32 = 25;
+///Commits per history page, the largest page GraphQL connections accept
+const HISTORY_PAGE_SIZE: u32 = 100;
+///Search never returns more results than this for a single query
+const SEARCH_RESULT_LIMIT: u32 = 1000;
+///Attempts of a query rejected by a rate limit, each waiting as long as GitHub asks
+const RATE_LIMIT_ATTEMPTS: u32 = 5;
+///Variables used by `REPOSITORY_FRAGMENT`, declared by every query including it
+const HISTORY_VARIABLES: &str =
+    "$history: Int!, $historyCursor: String, $since: GitTimestamp, $stats: Boolean!";
+///Repository with one page of the commit history of its default branch
+const REPOSITORY_FRAGMENT: &str = "
+fragment repository on Repository {
+  nameWithOwner
+  stargazerCount
+  isFork
+  isArchived
+  primaryLanguage { name }
+  defaultBranchRef {
+    name
+    target {
+      ... on Commit {
+        history(first: $history, after: $historyCursor, since: $since) {
+          pageInfo { hasNextPage endCursor }
+          nodes {
+            author { name email user { login } }
+            additions @include(if: $stats)
+            deletions @include(if: $stats)
+          }
+        }
+      }
+    }
+  }
+}";
+
+///Repositories and commit authors from the GitHub GraphQL API v4.
+///Searches and owner listings fetch the first history page of every repository in the same query,
+///so small repositories need no further request; longer histories are paged per repository.
+///Every query reports its cost, queries wait for the rate limit reset once the remaining points
+///no longer cover the last cost, rate limited queries are retried once GitHub allows it, and
+///batches shrink when GitHub rejects a query as too expensive.
+#[derive(Debug)]
+pub(crate) struct GraphQlHandler {
+    ///First history page of discovered repositories, `None` for repositories without commits
+    histories: Mutex<HashMap<String, Option<History>>>,
+    budget: Mutex<RateBudget>,
+    batch_size: AtomicU32,
+}
+
+impl Default for GraphQlHandler {
+    fn default() -> Self {
+        GraphQlHandler {
+            histories: Mutex::new(HashMap::new()),
+            budget: Mutex::new(RateBudget::default()),
+            batch_size: AtomicU32::new(REPOSITORY_BATCH),
+        }
+    }
+}
+
+///Points left in the current rate limit window, updated from every response
+#[derive(Debug, Default)]
+struct RateBudget {
+    remaining: Option<u64>,
+    ///Cost of the last query
+    cost: u64,
+    ///Unix timestamp the window resets at
+    reset: u64,
+}
+
+impl RateBudget {
+    ///Reserves the last cost from the remaining points, so concurrent queries can not spend the
+    ///same points. Time until the window resets when the remaining points do not cover it.
+    fn reserve(&mut self, now: u64) -> Option<Duration> {
+        let remaining = self.remaining?;
+        let cost = self.cost.max(1);
+        if remaining >= cost {
+            self.remaining = Some(remaining - cost);
+            return None;
+        }
+        (self.reset > now).then(|| Duration::from_secs(self.reset - now + 1))
+    }
+}
+
+///GitHub rejected a query by a primary or secondary rate limit, retried after the delay
+#[derive(Debug)]
+struct RateLimited(Duration);
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GitHub API rate limit exceeded")
+    }
+}
+impl std::error::Error for RateLimited {}
+
+///GitHub refused or timed out a query, a smaller batch may pass
+#[derive(Debug)]
+struct QueryTooExpensive;
+impl fmt::Display for QueryTooExpensive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GraphQL query exceeded GitHub resource limits")
+    }
+}
+impl std::error::Error for QueryTooExpensive {}
+
+impl GraphQlHandler {
+    ///Most starred repositories of every language. Search stops at 1000 results, so larger
+    ///searches continue below the fewest stars seen so far until `project_count` is reached.
+    pub(crate) async fn search_repositories(
+        &self,
+        client_details: Arc<HttpClientDetails>,
+        tx: Sender<RepositoryQueryResult>,
+    ) -> Result<()> {
+        let command = &client_details.command;
+        let qualifiers = search_qualifiers(command);
+        let query = format!(
+            "query($search: String!, $first: Int!, $cursor: String, {HISTORY_VARIABLES}) {{
+  rateLimit {{ cost remaining }}
+  search(query: $search, type: REPOSITORY, first: $first, after: $cursor) {{
+    repositoryCount
+    pageInfo {{ hasNextPage endCursor }}
+    nodes {{ ...repository }}
+  }}
+}}{REPOSITORY_FRAGMENT}"
+        );
+        let mut seen = HashSet::new();
+        for language in command.search_languages() {
+            let mut remaining = command.project_count as usize;
+            let mut ceiling = None;
+            while remaining > 0 {
+                let search = format!(
+                    "{} sort:stars-desc",
+                    create_search_query(
+                        &language,
+                        ceiling.map(|max| StarRange { min: 0, max }),
+                        &qualifiers
+                    )
+                );
+                let mut cursor: Option<String> = None;
+                let mut found = 0;
+                let mut lowest = None;
+                let total = loop {
+                    let data: SearchData = self
+                        .batched(&client_details, &query, |first| {
+                            with_history(
+                                command,
+                                json!({"search": search, "first": first, "cursor": cursor}),
+                            )
+                        })
+                        .await?;
+                    log::trace!("{:?}", &data);
+                    for node in data.search.nodes.into_iter().flatten() {
+                        lowest = Some(node.stargazer_count);
+                        if remaining == 0 {
+                            break;
+                        }
+                        if !seen.insert(node.name_with_owner.clone()) {
+                            log::debug!(
+                                "Skipping {}, already found by another search",
+                                node.name_with_owner
+                            );
+                            continue;
+                        }
+                        remaining -= 1;
+                        found += 1;
+                        tx.send(self.accept(node, client_details.clone()))
+                            .await
+                            .expect("Could not send message to contributor query handler");
+                    }
+                    let page_info = data.search.page_info;
+                    if !page_info.has_next_page || remaining == 0 {
+                        break data.search.repository_count;
+                    }
+                    cursor = page_info.end_cursor;
+                };
+                log::info!("Found {found} {language} repositories below {ceiling:?} stars");
+                if total <= SEARCH_RESULT_LIMIT || found == 0 {
+                    break;
+                }
+                ceiling = lowest;
+            }
+        }
+        Ok(())
+    }
+
+    ///Repositories of an organization or user, most starred first, stopping below `min_stars`
+    pub(crate) async fn fetch_owner_repositories(
+        &self,
+        owner: &OwnerScan,
+        client_details: Arc<HttpClientDetails>,
+        tx: Sender<RepositoryQueryResult>,
+    ) -> Result<()> {
+        let command = &client_details.command;
+        let query = format!(
+            "query($login: String!, $first: Int!, $cursor: String, {HISTORY_VARIABLES}) {{
+  rateLimit {{ cost remaining }}
+  repositoryOwner(login: $login) {{
+    repositories(first: $first, after: $cursor, orderBy: {{field: STARGAZERS, direction: DESC}}) {{
+      pageInfo {{ hasNextPage endCursor }}
+      nodes {{ ...repository }}
+    }}
+  }}
+}}{REPOSITORY_FRAGMENT}"
+        );
+        let mut cursor: Option<String> = None;
+        loop {
+            let data: OwnerData = self
+                .batched(&client_details, &query, |first| {
+                    with_history(
+                        command,
+                        json!({"login": owner.name, "first": first, "cursor": cursor}),
+                    )
+                })
+                .await?;
+            log::trace!("{:?}", &data);
+            let Some(repository_owner) = data.repository_owner else {
+                bail!("Owner {} not found", owner.name);
+            };
+            let repositories = repository_owner.repositories;
+            for node in repositories.nodes.into_iter().flatten() {
+                if node.stargazer_count < owner.min_stars {
+                    return Ok(());
+                }
+                if (node.is_fork && !owner.include_forks)
+                    || (node.is_archived && !owner.include_archived)
+                {
+                    log::debug!("Skipping filtered out {}", node.name_with_owner);
+                    continue;
+                }
+                tx.send(self.accept(node, client_details.clone()))
+                    .await
+                    .expect("Could not send message to contributor query handler");
+            }
+            if !repositories.page_info.has_next_page {
+                return Ok(());
+            }
+            cursor = repositories.page_info.end_cursor;
+        }
+    }
+
+    ///Looks up an `owner/name` repository, `None` when it does not exist
+    pub(crate) async fn fetch_repository(
+        &self,
+        repository: &str,
+        client_details: Arc<HttpClientDetails>,
+    ) -> Result<Option<RepositoryQueryResult>> {
+        let Some(node) = self
+            .query_repository(&client_details, repository, None)
+            .await?
+        else {
+            log::warn!("Repository {repository} not found");
+            return Ok(None);
+        };
+        Ok(Some(self.accept(node, client_details)))
+    }
+
+    ///Commit authors of the default branch, up to `max_commits` commits rounded up to whole history pages.
+    ///Returns contributors with the requested weight and whether the history was truncated.
+    pub(crate) async fn fetch_contributors(
+        &self,
+        data: &RepositoryQueryResult,
+    ) -> Result<(Vec<ContributorDetails>, bool)> {
+        let command = &data.client_details.command;
+        let cached = self.histories.lock().unwrap().remove(&data.project_name);
+        let history = match cached {
+            Some(history) => history,
+            None => self.fetch_history(data, None).await?,
+        };
+        let Some(mut history) = history else {
+            bail!(NoDataReason::EmptyRepository);
+        };
+        let mut commits = std::mem::take(&mut history.nodes);
+        let max_pages = command.max_commits.div_ceil(HISTORY_PAGE_SIZE);
+        let mut fetched_pages = 1;
+        while let (true, Some(cursor)) = (
+            history.page_info.has_next_page,
+            history.page_info.end_cursor.take(),
+        ) {
+            if fetched_pages >= max_pages {
+                log::warn!(
+                    "Commit history of {} truncated after {fetched_pages} pages",
+                    data.project_name
+                );
+                return Ok((summarize(commits, command), true));
+            }
+            history = self
+                .fetch_history(data, Some(cursor))
+                .await?
+                .ok_or(NoDataReason::EmptyRepository)?;
+            commits.append(&mut history.nodes);
+            fetched_pages += 1;
+        }
+        Ok((summarize(commits, command), false))
+    }
+
+    ///History page after `cursor`, `None` when the repository has no commits
+    async fn fetch_history(
+        &self,
+        data: &RepositoryQueryResult,
+        cursor: Option<String>,
+    ) -> Result<Option<History>> {
+        let node = self
+            .query_repository(&data.client_details, &data.project_name, cursor)
+            .await?
+            .ok_or(NoDataReason::AccessDenied)?;
+        Ok(node.history())
+    }
+
+    async fn query_repository(
+        &self,
+        client_details: &HttpClientDetails,
+        repository: &str,
+        history_cursor: Option<String>,
+    ) -> Result<Option<RepositoryNode>> {
+        let (owner, name) = repository
+            .split_once('/')
+            .with_context(|| format!("Repository must be given as owner/name, got {repository}"))?;
+        let query = format!(
+            "query($owner: String!, $name: String!, {HISTORY_VARIABLES}) {{
+  rateLimit {{ cost remaining }}
+  repository(owner: $owner, name: $name) {{ ...repository }}
+}}{REPOSITORY_FRAGMENT}"
+        );
+        let mut variables = with_history(
+            &client_details.command,
+            json!({"owner": owner, "name": name}),
+        );
+        variables["historyCursor"] = json!(history_cursor);
+        let data: RepositoryData = self.post(client_details, &query, variables).await?;
+        log::trace!("{:?}", &data);
+        Ok(data.repository)
+    }
+
+    ///Keeps the first history page for the contributor stage and converts the node
+    fn accept(
+        &self,
+        node: RepositoryNode,
+        client_details: Arc<HttpClientDetails>,
+    ) -> RepositoryQueryResult {
+        let repository_url = format!(
+            "{}/repos/{}",
+            api_url(&client_details.command),
+            node.name_with_owner
+        );
+        let default_branch = node
+            .default_branch_ref
+            .as_ref()
+            .map_or_else(|| "HEAD".to_string(), |branch| branch.name.clone());
+        let language = node
+            .primary_language
+            .as_ref()
+            .map(|language| language.name.clone())
+            .unwrap_or_default();
+        let project_name = node.name_with_owner.clone();
+        let stargazers = node.stargazer_count;
+        self.histories
+            .lock()
+            .unwrap()
+            .insert(project_name.clone(), node.history());
+        RepositoryQueryResult {
+            stargazers,
+            contributor_url: format!("{repository_url}/contributors"),
+            repository_url,
+            default_branch,
+            project_name,
+            language,
+            dependencies: vec![],
+            client_details,
+        }
+    }
+
+    ///Sends a paged query with the current batch size, halving it while GitHub rejects the query
+    async fn batched<T: DeserializeOwned>(
+        &self,
+        client_details: &HttpClientDetails,
+        query: &str,
+        variables: impl Fn(u32) -> Value + Send,
+    ) -> Result<T> {
+        loop {
+            let batch = self.batch_size.load(Ordering::Relaxed);
+            match self.post(client_details, query, variables(batch)).await {
+                Err(err) if batch > 1 && err.is::<QueryTooExpensive>() => {
+                    log::warn!(
+                        "GraphQL query of {batch} repositories too expensive, retrying with {}",
+                        batch / 2
+                    );
+                    self.batch_size.store(batch / 2, Ordering::Relaxed);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    ///Sends a query, waiting and retrying as long as GitHub asks while it is rate limited
+    async fn post<T: DeserializeOwned>(
+        &self,
+        client_details: &HttpClientDetails,
+        query: &str,
+        variables: Value,
+    ) -> Result<T> {
+        let mut attempt = 1;
+        loop {
+            match self.send(client_details, query, &variables).await {
+                Err(err) if attempt < RATE_LIMIT_ATTEMPTS => {
+                    let RateLimited(delay) = err.downcast::<RateLimited>()?;
+                    log::warn!(
+                        "GitHub API rate limit hit, retrying in {}s",
+                        delay.as_secs()
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(
+        &self,
+        client_details: &HttpClientDetails,
+        query: &str,
+        variables: &Value,
+    ) -> Result<T> {
+        loop {
+            let delay = self.budget.lock().unwrap().reserve(now());
+            let Some(delay) = delay else {
+                break;
+            };
+            log::warn!(
+                "GraphQL rate limit nearly exhausted, waiting {}s for the reset",
+                delay.as_secs()
+            );
+            tokio::time::sleep(delay).await;
+        }
+        let full_url = graphql_url(&client_details.command);
+        log::trace!("Targeting {full_url} with {variables}");
+        let response = client_details
+            .client
+            .post(full_url)
+            .json(&json!({"query": query, "variables": variables}))
+            .send()
+            .await?;
+        if let Some(delay) = rate_limit_delay(response.status(), response.headers(), now()) {
+            bail!(RateLimited(delay));
+        }
+        if matches!(
+            response.status(),
+            StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT
+        ) {
+            bail!(QueryTooExpensive);
+        }
+        let reset: Option<u64> = response
+            .headers()
+            .get("x-ratelimit-reset")
+            .and_then(|reset| reset.to_str().ok()?.parse().ok());
+        let response = response
+            .error_for_status()?
+            .json::<GraphQlResponse>()
+            .await?;
+        for error in &response.errors {
+            match error.kind.as_str() {
+                "RATE_LIMITED" => bail!(RateLimited(Duration::from_secs(
+                    reset.map_or(60, |reset| reset.saturating_sub(now()) + 1)
+                ))),
+                "MAX_NODE_LIMIT_EXCEEDED" | "RESOURCE_LIMITS_EXCEEDED" => {
+                    bail!(QueryTooExpensive)
+                }
+                _ => log::debug!("GraphQL error: {}", error.message),
+            }
+        }
+        let Some(data) = response.data else {
+            bail!(
+                "GraphQL query failed: {}",
+                response
+                    .errors
+                    .iter()
+                    .map(|error| &error.message)
+                    .join(", ")
+            );
+        };
+        if let Some(rate_limit) = data
+            .get("rateLimit")
+            .and_then(|rate_limit| RateLimit::deserialize(rate_limit).ok())
+        {
+            let mut budget = self.budget.lock().unwrap();
+            budget.remaining = Some(rate_limit.remaining);
+            budget.cost = rate_limit.cost;
+            budget.reset = reset.unwrap_or(budget.reset);
+        }
+        Ok(serde_json::from_value(data)?)
+    }
+}
+
+///`/graphql` next to the REST API, `/api/graphql` for GitHub Enterprise Server (`/api/v3`)
+fn graphql_url(command: &BusFactorQueryCommand) -> String {
+    let api_url = api_url(command);
+    match api_url.strip_suffix("/v3") {
+        Some(root) => format!("{root}/graphql"),
+        None => format!("{api_url}/graphql"),
+    }
+}
+
+///Adds variables of the history page to `variables`
+fn with_history(command: &BusFactorQueryCommand, mut variables: Value) -> Value {
+    variables["history"] = json!(HISTORY_PAGE_SIZE);
+    variables["historyCursor"] = Value::Null;
+    variables["since"] = json!(command.since.as_ref().map(TimeWindow::start_iso));
+    variables["stats"] = json!(command.weight != ContributionWeight::Commits);
+    variables
+}
+
+///Commits grouped by account, most contributions first. Unlinked commits are grouped by email
+///with `--anonymous` only, like the REST contributors endpoint; apps committing as `name[bot]` are bots.
+fn summarize(commits: Vec<CommitNode>, command: &BusFactorQueryCommand) -> Vec<ContributorDetails> {
+    let mut contributors: HashMap<String, ContributorDetails> = HashMap::new();
+    for commit in commits {
+        let Some(author) = commit.author else {
+            continue;
+        };
+        let (key, login, kind) = match (author.user, &author.name) {
+            (Some(user), _) => (user.login.clone(), Some(user.login), "User"),
+            (None, Some(name)) if name.ends_with("[bot]") => {
+                (name.clone(), Some(name.clone()), "Bot")
+            }
+            (None, _) if command.anonymous => (
+                author
+                    .email
+                    .clone()
+                    .or_else(|| author.name.clone())
+                    .unwrap_or_default(),
+                None,
+                "Anonymous",
+            ),
+            (None, _) => continue,
+        };
+        contributors
+            .entry(key)
+            .or_insert_with(|| ContributorDetails {
+                login,
+                name: author.name,
+                email: author.email,
+                kind: kind.to_string(),
+                contributions: 0,
+                other: Default::default(),
+            })
+            .contributions += command
+            .weight
+            .measure(1, commit.additions, commit.deletions);
+    }
+    contributors
+        .into_values()
+        .sorted_by_key(|contributor| {
+            (
+                Reverse(contributor.contributions),
+                contributor.login.clone(),
+                contributor.email.clone(),
+            )
+        })
+        .collect()
+}
+
+#[derive(Deserialize, Debug)]
+struct GraphQlResponse {
+    data: Option<Value>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+#[derive(Deserialize, Debug)]
+struct GraphQlError {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    message: String,
+}
+#[derive(Deserialize, Debug)]
+struct RateLimit {
+    cost: u64,
+    remaining: u64,
+}
+#[derive(Deserialize, Debug)]
+struct SearchData {
+    search: Connection,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct OwnerData {
+    repository_owner: Option<RepositoryOwner>,
+}
+#[derive(Deserialize, Debug)]
+struct RepositoryOwner {
+    repositories: Connection,
+}
+#[derive(Deserialize, Debug)]
+struct RepositoryData {
+    repository: Option<RepositoryNode>,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Connection {
+    #[serde(default)]
+    repository_count: u32,
+    page_info: PageInfo,
+    #[serde(default)]
+    nodes: Vec<Option<RepositoryNode>>,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RepositoryNode {
+    name_with_owner: String,
+    stargazer_count: u32,
+    #[serde(default)]
+    is_fork: bool,
+    #[serde(default)]
+    is_archived: bool,
+    primary_language: Option<Language>,
+    default_branch_ref: Option<BranchRef>,
+}
+impl RepositoryNode {
+    fn history(self) -> Option<History> {
+        self.default_branch_ref?.target?.history
+    }
+}
+#[derive(Deserialize, Debug)]
+struct Language {
+    name: String,
+}
+#[derive(Deserialize, Debug)]
+struct BranchRef {
+    name: String,
+    target: Option<CommitTarget>,
+}
+#[derive(Deserialize, Debug)]
+struct CommitTarget {
+    history: Option<History>,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct History {
+    page_info: PageInfo,
+    nodes: Vec<CommitNode>,
+}
+#[derive(Deserialize, Debug)]
+struct CommitNode {
+    author: Option<CommitAuthor>,
+    #[serde(default)]
+    additions: u64,
+    #[serde(default)]
+    deletions: u64,
+}
+#[derive(Deserialize, Debug)]
+struct CommitAuthor {
+    name: Option<String>,
+    email: Option<String>,
+    user: Option<User>,
+}
+#[derive(Deserialize, Debug)]
+struct User {
+    login: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn summarize_commit_authors() {
+        let commits: Vec<CommitNode> = serde_json::from_str(
+            r#"[
+                {"author": {"name": "Luke", "email": "luke@rebels.org", "user": {"login": "luke"}}, "additions": 10, "deletions": 2},
+                {"author": {"name": "Luke S.", "email": "luke@jedi.org", "user": {"login": "luke"}}, "additions": 1, "deletions": 0},
+                {"author": {"name": "dependabot[bot]", "email": "bot@github.com", "user": null}, "additions": 4, "deletions": 4},
+                {"author": {"name": "Leia", "email": "leia@rebels.org", "user": null}, "additions": 3, "deletions": 0}
+            ]"#,
+        )
+        .unwrap();
+        let contributors = summarize(
+            commits,
+            &BusFactorQueryCommand {
+                weight: ContributionWeight::Churn,
+                ..Default::default()
+            },
+        );
+        let summary: Vec<(Option<&str>, &str, u64)> = contributors
+            .iter()
+            .map(|contributor| {
+                (
+                    contributor.login.as_deref(),
+                    contributor.kind.as_str(),
+                    contributor.contributions,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Some("luke"), "User", 13),
+                (Some("dependabot[bot]"), "Bot", 8)
+            ]
+        );
+        let commits: Vec<CommitNode> = serde_json::from_str(
+            r#"[{"author": {"name": "Leia", "email": "leia@rebels.org", "user": null}}]"#,
+        )
+        .unwrap();
+        let contributors = summarize(
+            commits,
+            &BusFactorQueryCommand {
+                anonymous: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(contributors[0].email.as_deref(), Some("leia@rebels.org"));
+        assert_eq!(contributors[0].kind, "Anonymous");
+    }
+    #[test]
+    fn rate_budget_reserve() {
+        let mut budget = RateBudget {
+            remaining: Some(8),
+            cost: 5,
+            reset: 1_060,
+        };
+        assert_eq!(budget.reserve(1_000), None);
+        assert_eq!(budget.remaining, Some(3));
+        assert_eq!(budget.reserve(1_000), Some(Duration::from_secs(61)));
+        assert_eq!(budget.reserve(1_060), None);
+        assert_eq!(RateBudget::default().reserve(1_000), None);
+    }
+    #[test]
+    fn graphql_next_to_rest_api() {
+        assert_eq!(
+            graphql_url(&BusFactorQueryCommand::default()),
+            "https://api.github.com/graphql"
+        );
+        assert_eq!(
+            graphql_url(&BusFactorQueryCommand {
+                github_url: "https://git.example.com/api/v3/search/repositories".to_string(),
+                ..Default::default()
+            }),
+            "https://git.example.com/api/graphql"
+        );
+    }
+}
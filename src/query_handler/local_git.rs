@@ -0,0 +1,243 @@
+use crate::query_handler::NoDataReason;
+use crate::{AliasMap, TimeWindow};
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use tokio::process::Command;
+
+///Starts every commit header in `git log` output, cannot appear in names or paths
+const COMMIT_MARKER: char = '\0';
+///Commit header of author name and email, both resolved through `.mailmap`
+const COMMIT_FORMAT: &str = "--format=%x00%aN%x00%aE";
+
+///Contribution history read from a local clone with `git log`, for repositories no API can reach
+pub(crate) struct LocalGitHandler;
+
+///Work of one commit author, names and emails are resolved through the repository `.mailmap`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LocalAuthor {
+    pub(crate) name: String,
+    pub(crate) email: String,
+    pub(crate) commits: u64,
+    pub(crate) additions: u64,
+    pub(crate) deletions: u64,
+}
+
+impl LocalGitHandler {
+    ///Activity of every author reachable from `HEAD`, limited to commits within the window
+    pub(crate) async fn read_activity(
+        path: &Path,
+        window: Option<&TimeWindow>,
+    ) -> Result<Vec<LocalAuthor>> {
+        let mut args = vec![
+            "log".to_string(),
+            COMMIT_FORMAT.to_string(),
+            "--numstat".to_string(),
+        ];
+        if let Some(window) = window {
+            args.push(format!("--since=@{}", window.start()));
+        }
+        Ok(Self::summarize(&Self::git(path, &args).await?))
+    }
+
+    ///Authors of every commit within the window touching a file tracked at `HEAD`, from the newest to the oldest.
+    ///Authors are mapped to their canonical identity, matched by email or name.
+    pub(crate) async fn read_file_histories(
+        path: &Path,
+        max_files: usize,
+        aliases: &AliasMap,
+        window: Option<&TimeWindow>,
+    ) -> Result<HashMap<String, Vec<String>>> {
+        let mut args = vec![
+            "log".to_string(),
+            COMMIT_FORMAT.to_string(),
+            "--name-only".to_string(),
+            "--no-renames".to_string(),
+        ];
+        if let Some(window) = window {
+            args.push(format!("--since=@{}", window.start()));
+        }
+        let log = Self::git(path, &args).await?;
+        let tree = Self::git(
+            path,
+            &[
+                "ls-tree".to_string(),
+                "-r".to_string(),
+                "--name-only".to_string(),
+                "HEAD".to_string(),
+            ],
+        )
+        .await?;
+        let files: Vec<&str> = tree.lines().filter(|file| !file.is_empty()).collect();
+        if files.len() > max_files {
+            log::warn!(
+                "{} has {} files, only first {max_files} are considered",
+                path.display(),
+                files.len()
+            );
+        }
+        let files: HashSet<&str> = files.into_iter().take(max_files).collect();
+        Ok(Self::file_histories(&log, &files, aliases))
+    }
+
+    async fn git(path: &Path, args: &[String]) -> Result<String> {
+        log::trace!("Running git {} in {}", args.join(" "), path.display());
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(path)
+            .args(["-c", "core.quotePath=false"])
+            .args(args)
+            .output()
+            .await
+            .context("Could not run git")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("does not have any commits") {
+                bail!(NoDataReason::EmptyRepository);
+            }
+            bail!(
+                "git {} failed in {}: {}",
+                args.join(" "),
+                path.display(),
+                stderr.trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    ///Sums `--numstat` lines of commits by author, most commits first.
+    ///Binary files (`-` instead of line counts) only count as a commit.
+    fn summarize(log: &str) -> Vec<LocalAuthor> {
+        let mut authors: HashMap<(String, String), LocalAuthor> = HashMap::new();
+        let mut current = None;
+        for line in log.lines() {
+            if let Some(header) = line.strip_prefix(COMMIT_MARKER) {
+                let (name, email) = header.split_once(COMMIT_MARKER).unwrap_or((header, ""));
+                let key = (name.to_string(), email.to_string());
+                authors
+                    .entry(key.clone())
+                    .or_insert_with(|| LocalAuthor {
+                        name: key.0.clone(),
+                        email: key.1.clone(),
+                        commits: 0,
+                        additions: 0,
+                        deletions: 0,
+                    })
+                    .commits += 1;
+                current = Some(key);
+                continue;
+            }
+            let (Some(key), Some((additions, rest))) = (&current, line.split_once('\t')) else {
+                continue;
+            };
+            let deletions = rest.split('\t').next().unwrap_or_default();
+            if let Some(author) = authors.get_mut(key) {
+                author.additions += additions.parse::<u64>().unwrap_or_default();
+                author.deletions += deletions.parse::<u64>().unwrap_or_default();
+            }
+        }
+        authors
+            .into_values()
+            .sorted_by_key(|author| {
+                (
+                    Reverse(author.commits),
+                    author.name.clone(),
+                    author.email.clone(),
+                )
+            })
+            .collect()
+    }
+
+    ///Histories of the given files from `--name-only` output
+    fn file_histories(
+        log: &str,
+        files: &HashSet<&str>,
+        aliases: &AliasMap,
+    ) -> HashMap<String, Vec<String>> {
+        let mut histories: HashMap<String, Vec<String>> = HashMap::new();
+        let mut author = None;
+        for line in log.lines() {
+            if let Some(header) = line.strip_prefix(COMMIT_MARKER) {
+                let (name, email) = header.split_once(COMMIT_MARKER).unwrap_or((header, ""));
+                author = Some(
+                    aliases
+                        .canonical(email)
+                        .or_else(|| aliases.canonical(name))
+                        .unwrap_or(name)
+                        .to_string(),
+                );
+                continue;
+            }
+            if let (Some(author), true) = (&author, files.contains(line)) {
+                histories
+                    .entry(line.to_string())
+                    .or_default()
+                    .push(author.clone());
+            }
+        }
+        histories
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn summarize_numstat() {
+        let log = "\0Luke\0luke@rebels.org\n\
+                   \n\
+                   10\t2\tsrc/lib.rs\n\
+                   -\t-\tlogo.png\n\
+                   \0Leia\0leia@rebels.org\n\
+                   \n\
+                   3\t0\tREADME.md\n\
+                   \0Luke\0luke@rebels.org\n\
+                   \n\
+                   1\t1\tsrc/lib.rs\n\
+                   \0Luke\0luke@jedi.org\n";
+        assert_eq!(
+            LocalGitHandler::summarize(log),
+            vec![
+                LocalAuthor {
+                    name: "Luke".to_string(),
+                    email: "luke@rebels.org".to_string(),
+                    commits: 2,
+                    additions: 11,
+                    deletions: 3,
+                },
+                LocalAuthor {
+                    name: "Leia".to_string(),
+                    email: "leia@rebels.org".to_string(),
+                    commits: 1,
+                    additions: 3,
+                    deletions: 0,
+                },
+                LocalAuthor {
+                    name: "Luke".to_string(),
+                    email: "luke@jedi.org".to_string(),
+                    commits: 1,
+                    additions: 0,
+                    deletions: 0,
+                },
+            ]
+        );
+    }
+    #[test]
+    fn file_histories_of_tracked_files() {
+        let log = "\0Luke\0luke@rebels.org\n\
+                   \n\
+                   src/lib.rs\n\
+                   removed.rs\n\
+                   \0Leia\0leia@rebels.org\n\
+                   \n\
+                   src/lib.rs\n\
+                   README.md\n";
+        let files = HashSet::from(["src/lib.rs", "README.md"]);
+        let histories = LocalGitHandler::file_histories(log, &files, &AliasMap::default());
+        assert_eq!(histories.len(), 2);
+        assert_eq!(histories["src/lib.rs"], vec!["Luke", "Leia"]);
+        assert_eq!(histories["README.md"], vec!["Leia"]);
+    }
+}
@@ -1,14 +1,25 @@
 pub mod bus_factor;
+pub mod contributor_stats;
+pub mod gitea;
+pub mod gitlab;
+pub mod graphql;
+pub mod local_git;
+pub mod registry;
 pub mod repository;
+pub mod truck_factor;
 
-use crate::BusFactorQueryCommand;
-use anyhow::{anyhow, Result};
-use reqwest::header::HeaderMap;
-use std::env;
+use crate::{BusFactorQueryCommand, Dependency, FlagCriterion};
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::header::{HeaderMap, LINK};
+use reqwest::{Response, StatusCode, Url};
+use std::fmt;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 const USER_AGENT_VALUE: &str = "request";
+const SECONDARY_RATE_LIMIT_DELAY: Duration = Duration::from_secs(60);
 
+///HTTP client authenticated for the forge, shared by every request of one run
 #[derive(Debug)]
 pub struct HttpClientDetails {
     client: reqwest::Client,
@@ -16,31 +27,152 @@ pub struct HttpClientDetails {
 }
 #[derive(Debug)]
 pub struct BusFactorQueryResult {
-    pub login: String,
-    pub contributions: u32,
     pub repo_name: String,
-    pub bus_factor: u32,
+    ///Language whose search returned the project
+    pub language: String,
+    ///Dependencies developed in this repository, when scanning a manifest
+    pub dependencies: Vec<Dependency>,
     pub stargazers: u32,
+    ///Smallest number of top contributors covering the requested share of contributions
+    pub bus_factor: u32,
+    ///Logins of contributors counted into `bus_factor`
+    pub key_contributors: Vec<String>,
+    ///Contributors owning at least `threshold` percent of contributions, largest share first
+    pub dominant_contributors: Vec<DominantContributor>,
+    ///Contributor list was cut at `max_contributor_pages`, or the commit history at `max_commits`
+    pub truncated: bool,
+    ///Distribution of contributions over all contributors
+    pub concentration: ConcentrationMetrics,
+    ///Project passed `threshold` for the selected flagging criterion
+    pub flagged: bool,
+    ///Set when GitHub returned no contributor data to compute the bus factor from
+    pub no_data: Option<NoDataReason>,
+}
+impl BusFactorQueryResult {
+    pub(crate) fn no_data(data: &RepositoryQueryResult, reason: NoDataReason) -> Self {
+        BusFactorQueryResult {
+            language: data.language.clone(),
+            stargazers: data.stargazers,
+            ..Self::unavailable(&data.project_name, data.dependencies.clone(), reason)
+        }
+    }
+    ///Named repository which could not be looked up
+    pub(crate) fn unavailable(
+        repo_name: &str,
+        dependencies: Vec<Dependency>,
+        reason: NoDataReason,
+    ) -> Self {
+        BusFactorQueryResult {
+            repo_name: repo_name.to_string(),
+            language: String::new(),
+            dependencies,
+            stargazers: 0,
+            bus_factor: 0,
+            key_contributors: vec![],
+            dominant_contributors: vec![],
+            truncated: false,
+            concentration: ConcentrationMetrics::default(),
+            flagged: false,
+            no_data: Some(reason),
+        }
+    }
+}
+///Why a project has no contributor data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoDataReason {
+    ///Repository has no commits
+    EmptyRepository,
+    ///GitHub refuses to compute contributors of repositories with huge history
+    TooLarge,
+    ///Repository is private, blocked or missing
+    AccessDenied,
+    ///Nobody contributed within the requested window or weight
+    NoActivity,
+    ///GitHub did not finish computing contributor statistics
+    StatisticsPending,
+    ///Forge provider can not compute the requested strategy
+    Unsupported,
+    ///Named repository does not exist
+    NotFound,
+    ///Forge failed to answer for the repository
+    Unavailable,
+    ///Every contributor was dropped as a bot
+    OnlyBots,
 }
+impl fmt::Display for NoDataReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NoDataReason::EmptyRepository => write!(f, "empty repository"),
+            NoDataReason::TooLarge => write!(f, "contributor list too large for GitHub to compute"),
+            NoDataReason::AccessDenied => write!(f, "access denied"),
+            NoDataReason::NoActivity => write!(f, "no contributions in the requested window"),
+            NoDataReason::StatisticsPending => write!(f, "contributor statistics not computed yet"),
+            NoDataReason::Unsupported => write!(f, "not supported by the forge provider"),
+            NoDataReason::NotFound => write!(f, "repository not found"),
+            NoDataReason::Unavailable => write!(f, "forge failed to answer"),
+            NoDataReason::OnlyBots => write!(f, "only bot contributors"),
+        }
+    }
+}
+impl std::error::Error for NoDataReason {}
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ConcentrationMetrics {
+    pub contributors: usize,
+    ///Gini coefficient, 0 when everybody contributed equally, approaching 1 for maximal inequality
+    pub gini: f64,
+    ///Herfindahl-Hirschman index, sum of squared shares, from `1/contributors` up to 1
+    pub hhi: f64,
+    ///Shannon entropy of shares in bits, 0 for a single contributor
+    pub entropy: f64,
+    ///Share of the three biggest contributors, from 0 to 1
+    pub top3_share: f64,
+}
+impl ConcentrationMetrics {
+    ///Concentration in percent according to the criterion, higher means fewer people own the work.
+    ///Entropy is normalized by its maximum and inverted.
+    pub fn score(&self, criterion: FlagCriterion) -> Option<f64> {
+        let score = match criterion {
+            FlagCriterion::Dominant => return None,
+            FlagCriterion::Gini => self.gini,
+            FlagCriterion::Hhi => self.hhi,
+            FlagCriterion::Entropy if self.contributors > 1 => {
+                1.0 - self.entropy / (self.contributors as f64).log2()
+            }
+            FlagCriterion::Entropy => f64::from(u8::from(self.contributors == 1)),
+            FlagCriterion::Top3 => self.top3_share,
+        };
+        Some(100.0 * score)
+    }
+}
+#[derive(Debug)]
+pub struct DominantContributor {
+    pub login: String,
+    ///Contributions measured in the requested weight (commits or changed lines), authored files for the truck factor
+    pub contributions: u64,
+    pub percentage: u32,
+}
+///Repository found by a forge provider, URLs are opaque to everything but the provider itself
 #[derive(Debug, Clone)]
 pub struct RepositoryQueryResult {
-    pub(crate) stargazers: u32,
-    pub(crate) contributor_url: String,
-    pub(crate) project_name: String,
-    pub(crate) client_details: Arc<HttpClientDetails>,
+    pub stargazers: u32,
+    pub contributor_url: String,
+    ///API location of the repository, or the path of a local clone
+    pub repository_url: String,
+    pub default_branch: String,
+    pub project_name: String,
+    ///Language whose search returned the project, empty otherwise
+    pub language: String,
+    ///Dependencies developed in this repository, filled in by the pipeline when scanning a manifest
+    pub dependencies: Vec<Dependency>,
+    pub client_details: Arc<HttpClientDetails>,
 }
 
 impl HttpClientDetails {
-    fn new(command: BusFactorQueryCommand) -> Result<Self> {
-        let personal_access_token = env::var("GITHUB_ACCESS_TOKEN")
-            .map(|token| format!("token {}", token))
-            .map_err(|err| anyhow!("Missing GITHUB_ACCESS_TOKEN environment variable. {err}"))?;
-
-        let mut default_headers = HeaderMap::new();
-        default_headers.append(
-            reqwest::header::AUTHORIZATION,
-            reqwest::header::HeaderValue::from_str(&personal_access_token)?,
-        );
+    ///`default_headers` come from `ForgeProvider::authenticate`, a user agent is always added
+    pub(crate) fn new(
+        command: BusFactorQueryCommand,
+        mut default_headers: HeaderMap,
+    ) -> Result<Self> {
         default_headers.append(
             reqwest::header::USER_AGENT,
             reqwest::header::HeaderValue::from_str(USER_AGENT_VALUE)?,
@@ -52,5 +184,176 @@ impl HttpClientDetails {
             command,
         })
     }
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+    pub fn command(&self) -> &BusFactorQueryCommand {
+        &self.command
+    }
 }
 pub type Other = serde_json::Map<String, serde_json::Value>;
+
+///Converts responses meaning that there is no contributor data into `NoDataReason` errors
+pub(crate) async fn ensure_contributor_data(response: Response) -> Result<Response> {
+    match response.status() {
+        StatusCode::NO_CONTENT | StatusCode::CONFLICT => bail!(NoDataReason::EmptyRepository),
+        StatusCode::FORBIDDEN
+        | StatusCode::UNAUTHORIZED
+        | StatusCode::NOT_FOUND
+        | StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS => {
+            ensure_rate_limit_left(&response)?;
+            let body = response.text().await.unwrap_or_default();
+            if body.contains("too large") {
+                bail!(NoDataReason::TooLarge);
+            }
+            bail!(NoDataReason::AccessDenied)
+        }
+        _ => Ok(response.error_for_status()?),
+    }
+}
+
+///Response of a named repository lookup, `None` when the repository does not exist.
+///Repositories which can not be read are reported with a `NoDataReason` instead of failing the run.
+pub(crate) fn ensure_repository(response: Response) -> Result<Option<Response>> {
+    match response.status() {
+        StatusCode::NOT_FOUND | StatusCode::GONE => Ok(None),
+        StatusCode::FORBIDDEN
+        | StatusCode::UNAUTHORIZED
+        | StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS => {
+            ensure_rate_limit_left(&response)?;
+            bail!(NoDataReason::AccessDenied)
+        }
+        status if status.is_server_error() => bail!(NoDataReason::Unavailable),
+        _ => Ok(Some(response.error_for_status()?)),
+    }
+}
+
+///Exhausted rate limit fails the run, it would deny every following request as well
+fn ensure_rate_limit_left(response: &Response) -> Result<()> {
+    if response
+        .headers()
+        .get("x-ratelimit-remaining")
+        .is_some_and(|remaining| remaining == "0")
+    {
+        bail!("GitHub API rate limit exceeded");
+    }
+    Ok(())
+}
+
+///Time to wait before retrying a rate limited response: `retry-after` of secondary rate limits,
+///or until `x-ratelimit-reset` once the primary limit is exhausted. `None` for other responses.
+pub(crate) fn rate_limit_delay(
+    status: StatusCode,
+    headers: &HeaderMap,
+    now: u64,
+) -> Option<Duration> {
+    if !matches!(
+        status,
+        StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+    ) {
+        return None;
+    }
+    let header = |name: &str| -> Option<u64> { headers.get(name)?.to_str().ok()?.parse().ok() };
+    if let Some(retry_after) = header("retry-after") {
+        return Some(Duration::from_secs(retry_after));
+    }
+    if header("x-ratelimit-remaining") == Some(0) {
+        let reset = header("x-ratelimit-reset").unwrap_or(now);
+        return Some(Duration::from_secs(reset.saturating_sub(now) + 1));
+    }
+    //GitHub asks to wait at least a minute on secondary rate limits without further hints
+    (status == StatusCode::TOO_MANY_REQUESTS).then_some(SECONDARY_RATE_LIMIT_DELAY)
+}
+
+///Current Unix timestamp
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+///`{api_url}/repos/{owner}/{name}` with every segment of the repository percent-encoded
+pub(crate) fn repository_api_url(api_url: &str, repository: &str) -> Result<Url> {
+    let mut url = Url::parse(&format!("{}/repos", api_url.trim_end_matches('/')))
+        .map_err(|err| anyhow!(err))
+        .context("Could not create URL")?;
+    url.path_segments_mut()
+        .map_err(|_| anyhow!("Could not create URL from {api_url}"))?
+        .extend(repository.split('/'));
+    Ok(url)
+}
+
+///Extracts `rel="next"` URL from GitHub's `Link` pagination header
+pub(crate) fn next_page_url(headers: &HeaderMap) -> Option<Url> {
+    let link = headers.get(LINK)?.to_str().ok()?;
+    link.split(',').find_map(|entry| {
+        let (url, params) = entry.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == r#"rel="next""#)
+            .then(|| {
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .parse()
+                    .ok()
+            })
+            .flatten()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    fn link_headers(link: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(LINK, HeaderValue::from_str(link).unwrap());
+        headers
+    }
+    #[test]
+    fn rate_limit_delays() {
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("30"));
+        assert_eq!(
+            rate_limit_delay(StatusCode::FORBIDDEN, &headers, 1_000),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(rate_limit_delay(StatusCode::OK, &headers, 1_000), None);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("1060"));
+        assert_eq!(
+            rate_limit_delay(StatusCode::FORBIDDEN, &headers, 1_000),
+            Some(Duration::from_secs(61))
+        );
+        let headers = HeaderMap::new();
+        assert_eq!(
+            rate_limit_delay(StatusCode::FORBIDDEN, &headers, 1_000),
+            None
+        );
+        assert_eq!(
+            rate_limit_delay(StatusCode::TOO_MANY_REQUESTS, &headers, 1_000),
+            Some(SECONDARY_RATE_LIMIT_DELAY)
+        );
+    }
+    #[test]
+    fn next_page_url_present() {
+        let headers = link_headers(
+            r#"<https://api.github.com/repositories/1/contributors?page=1>; rel="prev", <https://api.github.com/repositories/1/contributors?page=3>; rel="next", <https://api.github.com/repositories/1/contributors?page=9>; rel="last""#,
+        );
+        assert_eq!(
+            next_page_url(&headers).unwrap().as_str(),
+            "https://api.github.com/repositories/1/contributors?page=3"
+        );
+    }
+    #[test]
+    fn next_page_url_missing() {
+        let headers = link_headers(
+            r#"<https://api.github.com/repositories/1/contributors?page=1>; rel="first", <https://api.github.com/repositories/1/contributors?page=2>; rel="prev""#,
+        );
+        assert!(next_page_url(&headers).is_none());
+        assert!(next_page_url(&HeaderMap::new()).is_none());
+    }
+}
@@ -0,0 +1,342 @@
+use crate::{Dependency, DependencyScan, Ecosystem};
+use anyhow::Result;
+use itertools::Itertools;
+use reqwest::{Client, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+///Registries ask API users to identify themselves
+const REGISTRY_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+const CONCURRENT_REGISTRY_REQUESTS: usize = 5;
+
+///Dependencies traced to their GitHub repositories
+#[derive(Debug, Default)]
+pub(crate) struct Resolution {
+    ///`owner/name` repositories with dependencies developed in them
+    pub(crate) repositories: Vec<(String, Vec<Dependency>)>,
+    pub(crate) unresolved: Vec<UnresolvedDependency>,
+}
+
+///Dependency whose source repository could not be found on GitHub
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct UnresolvedDependency {
+    pub(crate) dependency: Dependency,
+    pub(crate) reason: UnresolvedReason,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum UnresolvedReason {
+    ///Package is unknown to its registry
+    NotPublished,
+    ///Package metadata names no source repository
+    NoRepository,
+    ///Source repository is hosted elsewhere than on GitHub
+    NotOnGitHub(String),
+    ///Registry request failed, e.g. by a server error or unexpected metadata
+    LookupFailed(String),
+    ///Package comes from an ecosystem without a supported registry
+    UnsupportedEcosystem,
+}
+
+impl fmt::Display for UnresolvedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnresolvedReason::NotPublished => write!(f, "not published in the registry"),
+            UnresolvedReason::NoRepository => write!(f, "no repository in package metadata"),
+            UnresolvedReason::NotOnGitHub(url) => write!(f, "repository {url} is not on GitHub"),
+            UnresolvedReason::LookupFailed(err) => write!(f, "registry lookup failed: {err}"),
+            UnresolvedReason::UnsupportedEcosystem => write!(f, "ecosystem is not supported"),
+        }
+    }
+}
+
+///Finds GitHub repositories of dependencies through the `repository` field of package metadata:
+///crates.io (`/crates/{name}`), npm (`/{name}/latest`) and PyPI (`/pypi/{name}/json`).
+///A separate client is used so that the GitHub token is never sent to a registry.
+pub(crate) struct RegistryHandler;
+
+impl RegistryHandler {
+    pub(crate) async fn resolve(scan: &DependencyScan) -> Result<Resolution> {
+        let client = Client::builder().user_agent(REGISTRY_USER_AGENT).build()?;
+        let semaphore = Arc::new(Semaphore::new(CONCURRENT_REGISTRY_REQUESTS));
+        let mut requests = JoinSet::new();
+        for dependency in scan.dependencies.iter().cloned() {
+            let client = client.clone();
+            let scan = scan.clone();
+            let semaphore = semaphore.clone();
+            requests.spawn(async move {
+                let repository = match &dependency.repository {
+                    Some(repository) => Ok(repository.clone()),
+                    None => {
+                        let _permit = semaphore.acquire_owned().await?;
+                        Self::fetch_repository(&client, &scan, &dependency)
+                            .await
+                            .unwrap_or_else(|err| {
+                                Err(UnresolvedReason::LookupFailed(format!("{err:#}")))
+                            })
+                    }
+                };
+                Ok::<_, anyhow::Error>((dependency, repository))
+            });
+        }
+        let mut repositories: HashMap<String, Vec<Dependency>> = HashMap::new();
+        let mut unresolved = vec![];
+        while let Some(request) = requests.join_next().await {
+            let (dependency, repository) = request??;
+            let reason = match repository {
+                Ok(repository) => match github_repository(&repository) {
+                    Some(repository) => {
+                        repositories.entry(repository).or_default().push(dependency);
+                        continue;
+                    }
+                    None => UnresolvedReason::NotOnGitHub(repository),
+                },
+                Err(reason) => reason,
+            };
+            log::warn!(
+                "No GitHub repository found for {}: {reason}",
+                dependency.name
+            );
+            unresolved.push(UnresolvedDependency { dependency, reason });
+        }
+        Ok(Resolution {
+            repositories: repositories
+                .into_iter()
+                .sorted_by(|left, right| left.0.cmp(&right.0))
+                .map(|(repository, dependencies)| {
+                    let dependencies = dependencies
+                        .into_iter()
+                        .sorted_by(|left, right| {
+                            (left.kind, &left.name).cmp(&(right.kind, &right.name))
+                        })
+                        .collect();
+                    (repository, dependencies)
+                })
+                .collect(),
+            unresolved: unresolved
+                .into_iter()
+                .sorted_by(|left, right| {
+                    (left.dependency.kind, &left.dependency.name)
+                        .cmp(&(right.dependency.kind, &right.dependency.name))
+                })
+                .collect(),
+        })
+    }
+
+    ///Repository URL from package metadata
+    async fn fetch_repository(
+        client: &Client,
+        scan: &DependencyScan,
+        dependency: &Dependency,
+    ) -> Result<Result<String, UnresolvedReason>> {
+        let name = &dependency.name;
+        let repository = match dependency.ecosystem {
+            Ecosystem::Cargo => {
+                let full_url = format!("{}/crates/{name}", scan.crates_registry_url);
+                Self::fetch_metadata::<CrateResponse>(client, &full_url)
+                    .await?
+                    .map(|response| response.details.repository)
+            }
+            Ecosystem::Npm => {
+                let full_url = format!(
+                    "{}/{}/latest",
+                    scan.npm_registry_url,
+                    name.replace('/', "%2f")
+                );
+                Self::fetch_metadata::<NpmPackage>(client, &full_url)
+                    .await?
+                    .map(|package| package.repository.and_then(NpmRepository::into_url))
+            }
+            Ecosystem::PyPI => {
+                let full_url = format!("{}/pypi/{name}/json", scan.pypi_registry_url);
+                Self::fetch_metadata::<PypiResponse>(client, &full_url)
+                    .await?
+                    .map(|response| response.info.repository())
+            }
+            Ecosystem::Unknown => return Ok(Err(UnresolvedReason::UnsupportedEcosystem)),
+        };
+        Ok(match repository {
+            Some(Some(repository)) => Ok(repository),
+            Some(None) => Err(UnresolvedReason::NoRepository),
+            None => Err(UnresolvedReason::NotPublished),
+        })
+    }
+
+    ///`None` when the registry answers `404 Not Found`
+    async fn fetch_metadata<T: DeserializeOwned + fmt::Debug>(
+        client: &Client,
+        full_url: &str,
+    ) -> Result<Option<T>> {
+        log::trace!("Targeting {:?}", full_url);
+        let response = client.get(full_url).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response.error_for_status()?.json::<T>().await?;
+        log::trace!("{:?}", &response);
+        Ok(Some(response))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct CrateResponse {
+    #[serde(rename = "crate")]
+    details: CrateDetails,
+}
+#[derive(Deserialize, Debug)]
+struct CrateDetails {
+    repository: Option<String>,
+}
+#[derive(Deserialize, Debug)]
+struct NpmPackage {
+    repository: Option<NpmRepository>,
+}
+///Either `{"type": "git", "url": "..."}` or a shorthand such as `github:owner/name` or `owner/name`
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum NpmRepository {
+    Shorthand(String),
+    Details { url: Option<String> },
+}
+impl NpmRepository {
+    ///`None` for details without a URL, e.g. `{"type": "git"}`
+    fn into_url(self) -> Option<String> {
+        match self {
+            NpmRepository::Details { url } => url,
+            NpmRepository::Shorthand(url)
+                if !url.contains(':') && url.matches('/').count() == 1 =>
+            {
+                Some(format!("github:{url}"))
+            }
+            NpmRepository::Shorthand(url) => Some(url),
+        }
+    }
+}
+#[derive(Deserialize, Debug)]
+struct PypiResponse {
+    info: PypiInfo,
+}
+#[derive(Deserialize, Debug)]
+struct PypiInfo {
+    project_urls: Option<HashMap<String, String>>,
+    home_page: Option<String>,
+}
+impl PypiInfo {
+    ///First GitHub link among project URLs (sorted by label) and home page, or any link if none is on GitHub
+    fn repository(self) -> Option<String> {
+        let urls: Vec<String> = self
+            .project_urls
+            .unwrap_or_default()
+            .into_iter()
+            .sorted()
+            .map(|(_, url)| url)
+            .chain(self.home_page)
+            .filter(|url| !url.is_empty())
+            .collect();
+        urls.iter()
+            .find(|url| github_repository(url).is_some())
+            .or(urls.first())
+            .cloned()
+    }
+}
+
+///`owner/name` of a GitHub repository URL, also accepting git sources such as
+///`git+https://github.com/owner/name.git?branch=main#sha`, `git@github.com:owner/name.git`,
+///`github:owner/name` and links into the repository tree
+fn github_repository(url: &str) -> Option<String> {
+    let url = url.trim().trim_start_matches("git+");
+    let url = match url
+        .strip_prefix("github:")
+        .or_else(|| url.strip_prefix("git@github.com:"))
+    {
+        Some(path) => format!("https://github.com/{path}"),
+        None => url.to_string(),
+    };
+    let url = Url::parse(&url).ok()?;
+    if !matches!(url.host_str(), Some("github.com" | "www.github.com")) {
+        return None;
+    }
+    let mut segments = url.path_segments()?.filter(|segment| !segment.is_empty());
+    let owner = segments.next()?;
+    let name = segments.next()?.split('@').next()?.trim_end_matches(".git");
+    (!name.is_empty()).then(|| format!("{owner}/{name}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse_github_repository() {
+        assert_eq!(
+            github_repository("https://github.com/serde-rs/serde").as_deref(),
+            Some("serde-rs/serde")
+        );
+        assert_eq!(
+            github_repository("https://github.com/tokio-rs/tokio/tree/master/tokio-macros")
+                .as_deref(),
+            Some("tokio-rs/tokio")
+        );
+        assert_eq!(
+            github_repository("git+https://github.com/rust-lang/log.git?branch=main#abcdef")
+                .as_deref(),
+            Some("rust-lang/log")
+        );
+        assert_eq!(
+            github_repository("git+ssh://git@github.com/jestjs/jest.git#abcdef").as_deref(),
+            Some("jestjs/jest")
+        );
+        assert_eq!(
+            github_repository("git@github.com:facebook/react.git").as_deref(),
+            Some("facebook/react")
+        );
+        assert_eq!(
+            github_repository("github:stevemao/left-pad#abcdef").as_deref(),
+            Some("stevemao/left-pad")
+        );
+        assert_eq!(
+            github_repository("git+https://github.com/pallets/flask.git@2.3.0").as_deref(),
+            Some("pallets/flask")
+        );
+        assert_eq!(github_repository("https://gitlab.com/owner/name"), None);
+        assert_eq!(github_repository("https://github.com/owner"), None);
+    }
+    #[test]
+    fn npm_repository_shorthand() {
+        let package: NpmPackage =
+            serde_json::from_str(r#"{"repository": "expressjs/express"}"#).unwrap();
+        assert_eq!(
+            package.repository.unwrap().into_url().as_deref(),
+            Some("github:expressjs/express")
+        );
+        let package: NpmPackage = serde_json::from_str(
+            r#"{"repository": {"type": "git", "url": "git+https://github.com/facebook/react.git"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            package.repository.unwrap().into_url().as_deref(),
+            Some("git+https://github.com/facebook/react.git")
+        );
+        let package: NpmPackage =
+            serde_json::from_str(r#"{"repository": {"type": "git"}}"#).unwrap();
+        assert_eq!(package.repository.unwrap().into_url(), None);
+    }
+    #[test]
+    fn pypi_repository_prefers_github() {
+        let info: PypiInfo = serde_json::from_str(
+            r#"{
+                "project_urls": {"Documentation": "https://docs.example.org", "Source": "https://github.com/psf/requests"},
+                "home_page": "https://requests.readthedocs.io"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            info.repository().as_deref(),
+            Some("https://github.com/psf/requests")
+        );
+    }
+}
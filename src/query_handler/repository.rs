@@ -1,68 +1,414 @@
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, Mutex};
 
-use crate::query_handler::{HttpClientDetails, Other, RepositoryQueryResult};
-use crate::BusFactorQueryCommand;
+use crate::query_handler::{
+    ensure_repository, next_page_url, now, rate_limit_delay, repository_api_url,
+    BusFactorQueryResult, HttpClientDetails, NoDataReason, Other, RepositoryQueryResult,
+};
+use crate::{BusFactorQueryCommand, Dependency, ForgeProvider, OwnerKind, OwnerScan};
 use anyhow::{anyhow, Context, Result};
+use itertools::Itertools;
 use log;
 use reqwest::Url;
 use serde::Deserialize;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::Semaphore;
+use tokio::task::{JoinHandle, JoinSet};
 
 const PAGE_SIZE: u32 = 30;
+///Largest page size accepted by the Search API
+const MAX_PAGE_SIZE: u32 = 100;
+///Search API never returns more results than this for a single query
+const SEARCH_RESULT_LIMIT: u32 = 1000;
+const OWNER_PAGE_SIZE: &str = "100";
+///Search API allows 30 requests per minute, pages beyond that wait for the advertised reset
+const CONCURRENT_SEARCH_PAGES: usize = 5;
+///Attempts of a search page rejected by a rate limit, each waiting as long as GitHub asks
+const RATE_LIMIT_ATTEMPTS: u32 = 5;
+
+///Full names of repositories already sent, shared by searches of all languages
+type SeenRepositories = Arc<Mutex<HashSet<String>>>;
 
 pub(crate) struct RepositoryHandler;
 
+///Search of a single language feeding the shared channel
+#[derive(Clone)]
+struct LanguageSearch {
+    language: String,
+    client_details: Arc<HttpClientDetails>,
+    seen: SeenRepositories,
+    tx: Sender<RepositoryQueryResult>,
+}
+
+///Inclusive range of stargazer counts used to split a search into queries below `SEARCH_RESULT_LIMIT`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct StarRange {
+    pub(crate) min: u32,
+    pub(crate) max: u32,
+}
+
+impl StarRange {
+    ///Upper and lower half of the range, `None` when it holds a single star count
+    fn split(self) -> Option<(StarRange, StarRange)> {
+        (self.min < self.max).then(|| {
+            let middle = self.min + (self.max - self.min) / 2;
+            (
+                StarRange {
+                    min: middle + 1,
+                    max: self.max,
+                },
+                StarRange {
+                    min: self.min,
+                    max: middle,
+                },
+            )
+        })
+    }
+}
+
+impl fmt::Display for StarRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "stars:{}..{}", self.min, self.max)
+    }
+}
+
 impl RepositoryHandler {
-    pub(crate) async fn run(
+    ///`dependency_repositories` are repositories of resolved dependencies when scanning manifests.
+    ///Named repositories which can not be looked up are sent straight to `unavailable`.
+    pub(crate) async fn run<P: ForgeProvider>(
         command: BusFactorQueryCommand,
-    ) -> Result<Receiver<RepositoryQueryResult>> {
+        provider: Arc<P>,
+        dependency_repositories: Vec<(String, Vec<Dependency>)>,
+        unavailable: Sender<BusFactorQueryResult>,
+    ) -> Result<(Receiver<RepositoryQueryResult>, JoinHandle<Result<()>>)> {
+        let client_details = Arc::new(HttpClientDetails::new(
+            command.clone(),
+            provider.authenticate(&command)?,
+        )?);
         let (tx, rx) = channel(1000);
-        tokio::spawn(RepositoryHandler::fetch_data(command, tx));
+        let stage = tokio::spawn(async move {
+            RepositoryHandler::query_api(
+                provider,
+                client_details,
+                dependency_repositories,
+                tx,
+                unavailable,
+            )
+            .await
+            .context("Error during fetching repository API")
+        });
         log::trace!("RepositoryHandler initialised");
-        Ok(rx)
+        Ok((rx, stage))
+    }
+    ///Explicit and dependency repositories are looked up one by one, anything else is left to the provider
+    async fn query_api<P: ForgeProvider>(
+        provider: Arc<P>,
+        client_details: Arc<HttpClientDetails>,
+        dependency_repositories: Vec<(String, Vec<Dependency>)>,
+        tx: Sender<RepositoryQueryResult>,
+        unavailable: Sender<BusFactorQueryResult>,
+    ) -> Result<()> {
+        let command = &client_details.command;
+        let repositories: Vec<String> = command.repositories.iter().unique().cloned().collect();
+        if !repositories.is_empty() {
+            let repositories = repositories
+                .into_iter()
+                .map(|repository| (repository, vec![]))
+                .collect();
+            return Self::fetch_repositories(
+                provider,
+                repositories,
+                client_details,
+                tx,
+                unavailable,
+            )
+            .await;
+        }
+        if command.dependencies.is_some() {
+            return Self::fetch_repositories(
+                provider,
+                dependency_repositories,
+                client_details,
+                tx,
+                unavailable,
+            )
+            .await;
+        }
+        provider.discover(client_details, tx).await
     }
-    async fn fetch_data(command: BusFactorQueryCommand, tx: Sender<RepositoryQueryResult>) {
-        if let Err(err) = RepositoryHandler::query_api(command, tx).await {
-            eprintln!("Error during fetching repository API: {err}");
-            std::process::exit(1);
+    ///Looks up every given repository concurrently, attaching dependencies developed in it.
+    ///Missing repositories and `NoDataReason` lookup errors are reported per repository.
+    async fn fetch_repositories<P: ForgeProvider>(
+        provider: Arc<P>,
+        repositories: Vec<(String, Vec<Dependency>)>,
+        client_details: Arc<HttpClientDetails>,
+        tx: Sender<RepositoryQueryResult>,
+        unavailable: Sender<BusFactorQueryResult>,
+    ) -> Result<()> {
+        let mut requests = JoinSet::new();
+        for (repository, dependencies) in repositories {
+            let provider = provider.clone();
+            let client_details = client_details.clone();
+            requests.spawn(async move {
+                let project = provider.repository(client_details, &repository).await;
+                (repository, dependencies, project)
+            });
+        }
+        while let Some(request) = requests.join_next().await {
+            let (repository, dependencies, project) = request?;
+            let reason = match project {
+                Ok(Some(project)) => {
+                    tx.send(RepositoryQueryResult {
+                        dependencies,
+                        ..project
+                    })
+                    .await
+                    .expect("Could not send message to contributor query handler");
+                    continue;
+                }
+                Ok(None) => NoDataReason::NotFound,
+                Err(err) => err.downcast::<NoDataReason>()?,
+            };
+            log::warn!("Repository {repository} skipped: {reason}");
+            unavailable
+                .send(BusFactorQueryResult::unavailable(
+                    &repository,
+                    dependencies,
+                    reason,
+                ))
+                .await?;
         }
+        Ok(())
     }
-    async fn query_api(
-        command: BusFactorQueryCommand,
+    ///Walks every page of an owner listing or searches every language, most starred first
+    pub(crate) async fn discover(
+        client_details: Arc<HttpClientDetails>,
         tx: Sender<RepositoryQueryResult>,
     ) -> Result<()> {
-        let client_details = Arc::new(HttpClientDetails::new(command.clone())?);
-
-        let page_size = Self::determine_page_size(&command);
+        if let Some(owner) = &client_details.command.owner {
+            return Self::fetch_owner_repositories(owner, client_details.clone(), tx).await;
+        }
+        let seen: SeenRepositories = Arc::new(Mutex::new(HashSet::new()));
+        for language in client_details.command.search_languages() {
+            Self::search_language(LanguageSearch {
+                language,
+                client_details: client_details.clone(),
+                seen: seen.clone(),
+                tx: tx.clone(),
+            })
+            .await?;
+        }
+        Ok(())
+    }
+    ///Looks up an `owner/name` repository via `/repos/{owner}/{repo}`, `None` when it does not exist
+    pub(crate) async fn fetch_repository(
+        repository: &str,
+        client_details: Arc<HttpClientDetails>,
+    ) -> Result<Option<RepositoryQueryResult>> {
+        let full_url = create_repository_url(&client_details.command, repository)?;
+        log::trace!("Targeting {:?}", &full_url);
+        let response = client_details.client.get(full_url).send().await?;
+        let Some(response) = ensure_repository(response)? else {
+            log::warn!("Repository {repository} not found");
+            return Ok(None);
+        };
+        let project = response
+            .error_for_status()?
+            .json::<RepositoryDetails>()
+            .await?;
+        log::trace!("{:?}", &project);
+        Ok(Some(project.into_query_result(client_details)))
+    }
+    ///Walks all pages of `/orgs/{org}/repos` or `/users/{user}/repos`, skipping filtered out repositories
+    async fn fetch_owner_repositories(
+        owner: &OwnerScan,
+        client_details: Arc<HttpClientDetails>,
+        tx: Sender<RepositoryQueryResult>,
+    ) -> Result<()> {
+        let mut next_page = Some(create_owner_url(&client_details.command, owner)?);
+        while let Some(full_url) = next_page {
+            log::trace!("Targeting {:?}", &full_url);
+            let response = client_details
+                .client
+                .get(full_url)
+                .send()
+                .await?
+                .error_for_status()?;
+            next_page = next_page_url(response.headers());
+            let projects = response.json::<Vec<RepositoryDetails>>().await?;
+            log::trace!("{:?}", &projects);
+            for project in projects {
+                if !owner.includes(&project) {
+                    log::debug!("Skipping filtered out {}", project.full_name);
+                    continue;
+                }
+                tx.send(project.into_query_result(client_details.clone()))
+                    .await
+                    .expect("Could not send message to contributor query handler");
+            }
+        }
+        Ok(())
+    }
+    ///Fetches top `project_count` projects of a single language
+    async fn search_language(search: LanguageSearch) -> Result<()> {
+        let command = &search.client_details.command;
+        let page_size = Self::determine_page_size(command);
 
-        let initial_response_count = Self::fetch_page_of_results(
-            create_repo_url(1, &client_details, page_size)?,
-            client_details.clone(),
-            tx.clone(),
+        let initial_response = Self::fetch_page_of_results(
+            create_repo_url(1, command, &search.language, None, page_size)?,
+            &search.client_details,
         )
         .await?;
+        let total_count = initial_response.total_count;
 
-        let page_request_count = Self::determine_page_count(command.project_count, page_size);
+        if command.project_count > SEARCH_RESULT_LIMIT && total_count > SEARCH_RESULT_LIMIT {
+            let max_stars = initial_response
+                .items
+                .first()
+                .map_or(0, |project| project.stargazers_count);
+            return Self::search_star_ranges(
+                search,
+                StarRange {
+                    min: 0,
+                    max: max_stars,
+                },
+            )
+            .await;
+        }
+
+        let project_count = command.project_count.min(total_count);
+        let page_request_count = Self::determine_page_count(project_count, page_size);
 
         log::info!(
-            "Total items to fetch = {}, pages = {}",
-            initial_response_count,
+            "Total {} items to fetch = {}, pages = {}",
+            search.language,
+            total_count,
             page_request_count
         );
 
-        if initial_response_count > page_size {
-            for request_no in 2..=page_request_count {
-                let full_url = create_repo_url(request_no, &client_details, page_size)?;
-                tokio::spawn(Self::fetch_page_of_results(
-                    full_url,
-                    client_details.clone(),
-                    tx.clone(),
+        search
+            .send_data(
+                initial_response,
+                Self::determine_page_limit(1, project_count, page_size),
+            )
+            .await;
+        //Pages are awaited, so a repository found by several languages keeps the first one
+        let pages = (2..=page_request_count)
+            .map(|request_no| {
+                Ok((
+                    create_repo_url(request_no, command, &search.language, None, page_size)?,
+                    Self::determine_page_limit(request_no, project_count, page_size),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::fetch_pages(&search, pages).await
+    }
+    ///Searches consecutive star ranges, highest first, each below `SEARCH_RESULT_LIMIT` results.
+    ///Exactly `project_count` projects are sent in descending star order.
+    async fn search_star_ranges(search: LanguageSearch, stars: StarRange) -> Result<()> {
+        let command = &search.client_details.command;
+        let ranges = Self::partition(&search, stars).await?;
+        log::info!(
+            "Total {} items to fetch = {}, star ranges = {}",
+            search.language,
+            command.project_count,
+            ranges.len()
+        );
+
+        let mut remaining = command.project_count;
+        let mut pages = vec![];
+        for (stars, count) in ranges {
+            let range_count = count.min(SEARCH_RESULT_LIMIT).min(remaining);
+            for request_no in 1..=Self::determine_page_count(range_count, MAX_PAGE_SIZE) {
+                pages.push((
+                    create_repo_url(
+                        request_no,
+                        command,
+                        &search.language,
+                        Some(stars),
+                        MAX_PAGE_SIZE,
+                    )?,
+                    Self::determine_page_limit(request_no, range_count, MAX_PAGE_SIZE),
                 ));
             }
+            remaining -= range_count;
+            if remaining == 0 {
+                break;
+            }
+        }
+        Self::fetch_pages(&search, pages).await
+    }
+    ///Fetches search pages with at most `CONCURRENT_SEARCH_PAGES` requests in flight, sending
+    ///each page in the given order up to its limit. The first failing page stops the search.
+    async fn fetch_pages(search: &LanguageSearch, pages: Vec<(Url, usize)>) -> Result<()> {
+        let semaphore = Arc::new(Semaphore::new(CONCURRENT_SEARCH_PAGES));
+        let mut requests = JoinSet::new();
+        let mut limits = vec![];
+        for (index, (full_url, limit)) in pages.into_iter().enumerate() {
+            limits.push(limit);
+            let client_details = search.client_details.clone();
+            let semaphore = semaphore.clone();
+            requests.spawn(async move {
+                let _permit = semaphore.acquire_owned().await?;
+                let page = Self::fetch_page_of_results(full_url, &client_details).await?;
+                Ok::<_, anyhow::Error>((index, page))
+            });
+        }
+        let mut fetched: Vec<Option<StargazersQueryResponse>> =
+            limits.iter().map(|_| None).collect();
+        let mut next = 0;
+        while let Some(request) = requests.join_next().await {
+            let (index, page) = request??;
+            fetched[index] = Some(page);
+            while let Some(page) = fetched.get_mut(next).and_then(Option::take) {
+                search.send_data(page, limits[next]).await;
+                next += 1;
+            }
         }
-
         Ok(())
     }
+    ///Recursively bisects `stars` until every range matches at most `SEARCH_RESULT_LIMIT` projects.
+    ///Returns ranges with their result counts, highest stars first,
+    ///stopping once they cover `project_count` projects.
+    async fn partition(search: &LanguageSearch, stars: StarRange) -> Result<Vec<(StarRange, u32)>> {
+        let command = &search.client_details.command;
+        let mut pending = vec![stars];
+        let mut ranges = vec![];
+        let mut covered = 0;
+        while let Some(stars) = pending.pop() {
+            if covered >= command.project_count {
+                break;
+            }
+            let count = Self::fetch_page_of_results(
+                create_repo_url(1, command, &search.language, Some(stars), 1)?,
+                &search.client_details,
+            )
+            .await?
+            .total_count;
+            log::debug!("{} {stars} matches {count} projects", search.language);
+            match stars.split() {
+                Some((upper, lower)) if count > SEARCH_RESULT_LIMIT => {
+                    pending.push(lower);
+                    pending.push(upper);
+                }
+                _ => {
+                    if count > SEARCH_RESULT_LIMIT {
+                        log::warn!(
+                            "{count} {} projects have {} stars, only first {SEARCH_RESULT_LIMIT} are available",
+                            search.language,
+                            stars.min
+                        );
+                    }
+                    if count > 0 {
+                        covered += count.min(SEARCH_RESULT_LIMIT);
+                        ranges.push((stars, count));
+                    }
+                }
+            }
+        }
+        Ok(ranges)
+    }
     fn determine_page_count(project_count: u32, page_size: u32) -> u32 {
         let mut page_request_count = project_count / page_size;
         if project_count % page_size > 0 {
@@ -78,52 +424,85 @@ impl RepositoryHandler {
             PAGE_SIZE
         }
     }
+    ///Number of projects taken from the given page so that exactly `project_count` are sent
+    fn determine_page_limit(request_no: u32, project_count: u32, page_size: u32) -> usize {
+        project_count
+            .saturating_sub((request_no - 1) * page_size)
+            .min(page_size) as usize
+    }
     async fn fetch_page_of_results(
         full_url: Url,
-        client_details: Arc<HttpClientDetails>,
-        tx: Sender<RepositoryQueryResult>,
-    ) -> Result<u32> {
-        log::trace!("Targeting {:?}", &full_url);
-        let request = client_details.client.get(full_url);
-        log::trace!("{:?}", &request);
-        let response = request
-            .send()
-            .await?
-            .json::<StargazersQueryResponse>()
-            .await?;
-        log::trace!("{:?}", &response);
-        let total_count = response.total_count;
-        tokio::spawn(Self::send_data(response, tx, client_details));
-        Ok(total_count)
+        client_details: &HttpClientDetails,
+    ) -> Result<StargazersQueryResponse> {
+        let mut attempt = 1;
+        loop {
+            log::trace!("Targeting {:?}", &full_url);
+            let request = client_details.client.get(full_url.clone());
+            log::trace!("{:?}", &request);
+            let response = request.send().await?;
+            match rate_limit_delay(response.status(), response.headers(), now()) {
+                Some(delay) if attempt < RATE_LIMIT_ATTEMPTS => {
+                    log::warn!(
+                        "Search API rate limit hit, retrying in {}s",
+                        delay.as_secs()
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                _ => {
+                    let response = response
+                        .error_for_status()?
+                        .json::<StargazersQueryResponse>()
+                        .await?;
+                    log::trace!("{:?}", &response);
+                    return Ok(response);
+                }
+            }
+        }
     }
-    async fn send_data(
-        response: StargazersQueryResponse,
-        tx: Sender<RepositoryQueryResult>,
-        client_details: Arc<HttpClientDetails>,
-    ) {
-        for project in response.items {
-            tx.send(RepositoryQueryResult {
-                stargazers: project.stargazers_count,
-                contributor_url: project.contributors_url,
-                project_name: project.full_name,
-                client_details: client_details.clone(),
-            })
-            .await
-            .expect("Could not send message to contributor query handler");
+}
+
+impl LanguageSearch {
+    async fn send_data(&self, response: StargazersQueryResponse, limit: usize) {
+        for project in response.items.into_iter().take(limit) {
+            if !self
+                .seen
+                .lock()
+                .expect("Repository registry poisoned")
+                .insert(project.full_name.clone())
+            {
+                log::debug!(
+                    "Skipping {}, already found by another search",
+                    project.full_name
+                );
+                continue;
+            }
+            let project = RepositoryDetails {
+                language: Some(self.language.clone()),
+                ..project
+            };
+            self.tx
+                .send(project.into_query_result(self.client_details.clone()))
+                .await
+                .expect("Could not send message to contributor query handler");
         }
     }
 }
 fn create_repo_url(
     request_no: u32,
-    client_details: &Arc<HttpClientDetails>,
+    command: &BusFactorQueryCommand,
+    language: &str,
+    stars: Option<StarRange>,
     page_size: u32,
 ) -> Result<Url> {
+    let qualifiers = search_qualifiers(command);
     reqwest::Url::parse_with_params(
-        &format!(
-            "{}?q=language:{}",
-            client_details.command.github_url, client_details.command.language
-        ),
+        &command.github_url,
         &[
+            (
+                "q",
+                create_search_query(language, stars, &qualifiers).as_str(),
+            ),
             ("sort", "stars"),
             ("order", "desc"),
             ("page", &request_no.to_string()),
@@ -133,6 +512,63 @@ fn create_repo_url(
     .map_err(|err| anyhow!(err))
     .context("Could not create URL")
 }
+///`topic:` qualifiers of requested topics followed by user supplied qualifiers
+pub(crate) fn search_qualifiers(command: &BusFactorQueryCommand) -> Vec<String> {
+    command
+        .topics
+        .iter()
+        .filter(|topic| !topic.trim().is_empty())
+        .map(|topic| format!("topic:{}", topic.trim()))
+        .chain(command.query.iter().cloned())
+        .collect()
+}
+///Root of the REST API the search endpoint belongs to, e.g. `https://api.github.com`
+pub(crate) fn api_url(command: &BusFactorQueryCommand) -> &str {
+    let github_url = command.github_url.trim_end_matches('/');
+    github_url
+        .strip_suffix("/search/repositories")
+        .unwrap_or(github_url)
+}
+///`/repos/{owner}/{repo}` next to the search endpoint, e.g. `https://api.github.com/repos/rust-lang/rust`
+fn create_repository_url(command: &BusFactorQueryCommand, repository: &str) -> Result<Url> {
+    repository_api_url(api_url(command), repository)
+}
+///First page of `/orgs/{org}/repos` or `/users/{user}/repos`
+fn create_owner_url(command: &BusFactorQueryCommand, owner: &OwnerScan) -> Result<Url> {
+    let owners = match owner.kind {
+        OwnerKind::Organization => "orgs",
+        OwnerKind::User => "users",
+    };
+    let mut url = Url::parse_with_params(
+        &format!("{}/{owners}", api_url(command).trim_end_matches('/')),
+        &[("type", "all"), ("per_page", OWNER_PAGE_SIZE)],
+    )
+    .map_err(|err| anyhow!(err))
+    .context("Could not create URL")?;
+    url.path_segments_mut()
+        .map_err(|_| anyhow!("Could not create URL from {}", api_url(command)))?
+        .extend([owner.name.as_str(), "repos"]);
+    Ok(url)
+}
+///Language qualifier (none for topic-only searches), optional star range and user supplied qualifiers, e.g. `language:rust stars:>1000`
+pub(crate) fn create_search_query(
+    language: &str,
+    stars: Option<StarRange>,
+    query: &[String],
+) -> String {
+    let language = if language.is_empty() {
+        String::new()
+    } else if language.contains(char::is_whitespace) {
+        format!("language:\"{language}\"")
+    } else {
+        format!("language:{language}")
+    };
+    std::iter::once(language)
+        .chain(stars.map(|stars| stars.to_string()))
+        .chain(query.iter().map(|qualifier| qualifier.trim().to_string()))
+        .filter(|qualifier| !qualifier.is_empty())
+        .join(" ")
+}
 
 #[derive(Deserialize, Debug)]
 #[allow(dead_code)]
@@ -147,10 +583,44 @@ pub struct StargazersQueryResponse {
 struct RepositoryDetails {
     stargazers_count: u32,
     contributors_url: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default = "default_branch")]
+    default_branch: String,
     full_name: String,
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    fork: bool,
+    #[serde(default)]
+    archived: bool,
     #[serde(flatten, skip)]
     other: Other,
 }
+impl RepositoryDetails {
+    fn into_query_result(self, client_details: Arc<HttpClientDetails>) -> RepositoryQueryResult {
+        RepositoryQueryResult {
+            stargazers: self.stargazers_count,
+            contributor_url: self.contributors_url,
+            repository_url: self.url,
+            default_branch: self.default_branch,
+            project_name: self.full_name,
+            language: self.language.unwrap_or_default(),
+            dependencies: vec![],
+            client_details,
+        }
+    }
+}
+impl OwnerScan {
+    fn includes(&self, project: &RepositoryDetails) -> bool {
+        (self.include_forks || !project.fork)
+            && (self.include_archived || !project.archived)
+            && project.stargazers_count >= self.min_stars
+    }
+}
+fn default_branch() -> String {
+    "HEAD".to_string()
+}
 
 #[cfg(test)]
 mod tests {
@@ -167,18 +637,136 @@ mod tests {
         assert_eq!(result, 3);
     }
     #[test]
+    fn repo_url_encoding() {
+        let url = create_repo_url(
+            2,
+            &BusFactorQueryCommand {
+                query: vec![
+                    "stars:>1000".to_string(),
+                    "topic:game-engine fork:false".to_string(),
+                ],
+                ..Default::default()
+            },
+            "c++",
+            None,
+            30,
+        )
+        .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.github.com/search/repositories?q=language%3Ac%2B%2B+stars%3A%3E1000+topic%3Agame-engine+fork%3Afalse&sort=stars&order=desc&page=2&per_page=30"
+        );
+        let query_pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        assert_eq!(
+            query_pairs[0],
+            (
+                "q".to_string(),
+                "language:c++ stars:>1000 topic:game-engine fork:false".to_string()
+            )
+        );
+    }
+    #[test]
+    fn repo_url_topic_only() {
+        let url = create_repo_url(
+            1,
+            &BusFactorQueryCommand {
+                topics: vec!["game-engine".to_string(), " ".to_string()],
+                query: vec!["fork:false".to_string()],
+                ..Default::default()
+            },
+            "",
+            None,
+            30,
+        )
+        .unwrap();
+        let query_pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        assert_eq!(
+            query_pairs[0],
+            ("q".to_string(), "topic:game-engine fork:false".to_string())
+        );
+    }
+    #[test]
+    fn search_query_quotes_language() {
+        let query = create_search_query("Visual Basic .NET", None, &["  ".to_string()]);
+        assert_eq!(query, "language:\"Visual Basic .NET\"");
+    }
+    #[test]
     fn calculate_page_size() {
         let result = RepositoryHandler::determine_page_size(&BusFactorQueryCommand {
-            language: "test".to_string(),
+            languages: vec!["test".to_string()],
             project_count: 25,
-            github_url: "".to_string(),
+            ..Default::default()
         });
         assert_eq!(result, 25);
         let result = RepositoryHandler::determine_page_size(&BusFactorQueryCommand {
-            language: "test".to_string(),
+            languages: vec!["test".to_string()],
             project_count: 35,
-            github_url: "".to_string(),
+            ..Default::default()
         });
         assert_eq!(result, 30);
     }
+    #[test]
+    fn calculate_page_limit() {
+        assert_eq!(RepositoryHandler::determine_page_limit(1, 40, 30), 30);
+        assert_eq!(RepositoryHandler::determine_page_limit(2, 40, 30), 10);
+        assert_eq!(RepositoryHandler::determine_page_limit(3, 40, 30), 0);
+    }
+    #[test]
+    fn split_star_range() {
+        let (upper, lower) = StarRange { min: 0, max: 10 }.split().unwrap();
+        assert_eq!(upper, StarRange { min: 6, max: 10 });
+        assert_eq!(lower, StarRange { min: 0, max: 5 });
+        assert_eq!(StarRange { min: 7, max: 7 }.split(), None);
+    }
+    #[test]
+    fn search_query_with_star_range() {
+        let query = create_search_query(
+            "rust",
+            Some(StarRange { min: 100, max: 200 }),
+            &["fork:false".to_string()],
+        );
+        assert_eq!(query, "language:rust stars:100..200 fork:false");
+    }
+    #[test]
+    fn repository_url_next_to_search() {
+        let url =
+            create_repository_url(&BusFactorQueryCommand::default(), "rust-lang/rust").unwrap();
+        assert_eq!(url.as_str(), "https://api.github.com/repos/rust-lang/rust");
+        let url = create_repository_url(
+            &BusFactorQueryCommand {
+                github_url: "https://github.example.com/api/v3/search/repositories".to_string(),
+                ..Default::default()
+            },
+            "rust-lang/rust",
+        )
+        .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://github.example.com/api/v3/repos/rust-lang/rust"
+        );
+    }
+    #[test]
+    fn owner_url() {
+        let owner = OwnerScan {
+            kind: OwnerKind::User,
+            name: "octocat".to_string(),
+            include_forks: false,
+            include_archived: false,
+            min_stars: 0,
+        };
+        let url = create_owner_url(&BusFactorQueryCommand::default(), &owner).unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.github.com/users/octocat/repos?type=all&per_page=100"
+        );
+        let owner = OwnerScan {
+            name: "../admin?x=1".to_string(),
+            ..owner
+        };
+        let url = create_owner_url(&BusFactorQueryCommand::default(), &owner).unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.github.com/users/..%2Fadmin%3Fx=1/repos?type=all&per_page=100"
+        );
+    }
 }
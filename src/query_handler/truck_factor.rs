@@ -0,0 +1,398 @@
+use crate::query_handler::bus_factor::{AuthorWeight, ContributorHandler};
+use crate::query_handler::{
+    ensure_contributor_data, next_page_url, BusFactorQueryResult, NoDataReason, Other,
+    RepositoryQueryResult,
+};
+use crate::{BotPolicy, BusFactorQueryCommand};
+use anyhow::{anyhow, bail, Context, Result};
+use itertools::Itertools;
+use reqwest::Url;
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+///Degree-of-authorship model coefficients (Fritz et al.), as used by Avelino et al.
+const DOA_BASE: f64 = 3.293;
+const DOA_FIRST_AUTHORSHIP: f64 = 1.098;
+const DOA_DELIVERIES: f64 = 0.164;
+const DOA_ACCEPTANCES: f64 = 0.321;
+///Minimal normalized degree of authorship to consider developer an author of a file
+const DOA_NORMALIZED_THRESHOLD: f64 = 0.75;
+const CONCURRENT_FILE_REQUESTS: usize = 10;
+const COMMITS_PAGE_SIZE: &str = "100";
+
+///Truck factor as defined by Avelino et al. "A novel approach for estimating truck factors".
+///Number of top authors which have to leave before more than half of the files are orphaned.
+pub(crate) struct TruckFactorHandler;
+
+impl TruckFactorHandler {
+    ///`histories` hold commit authors of every file, from the newest to the oldest commit
+    pub(crate) fn calculate(
+        data: &RepositoryQueryResult,
+        histories: HashMap<String, Vec<String>>,
+    ) -> Result<BusFactorQueryResult> {
+        if histories.is_empty() {
+            bail!(NoDataReason::EmptyRepository);
+        }
+        let command = &data.client_details.command;
+        let histories = Self::drop_bots(histories, command);
+        if histories.is_empty() {
+            bail!(NoDataReason::OnlyBots);
+        }
+        let file_authors: HashMap<String, Vec<String>> = histories
+            .into_iter()
+            .map(|(file, history)| (file, Self::file_authors(&history)))
+            .collect();
+        let key_contributors = Self::truck_factor(&file_authors);
+        //Authored files stand in for contributions, also when looking for dominant authors
+        let authorships: Vec<AuthorWeight> = file_authors
+            .values()
+            .flatten()
+            .counts()
+            .into_iter()
+            .map(|(author, count)| AuthorWeight {
+                identity: author.clone(),
+                weight: count as u64,
+                bot: ContributorHandler::is_bot(author, false, command),
+            })
+            .collect();
+        let concentration =
+            ContributorHandler::concentration(authorships.iter().map(|author| author.weight));
+        let dominant_contributors =
+            ContributorHandler::detect_bus_factor(&authorships, &data.project_name, command);
+        log::trace!(
+            "Project {} has truck factor {}",
+            data.project_name,
+            key_contributors.len()
+        );
+        Ok(BusFactorQueryResult {
+            repo_name: data.project_name.clone(),
+            language: data.language.clone(),
+            dependencies: data.dependencies.clone(),
+            stargazers: data.stargazers,
+            bus_factor: key_contributors.len() as u32,
+            key_contributors,
+            no_data: None,
+            flagged: ContributorHandler::is_flagged(
+                &dominant_contributors,
+                &concentration,
+                command,
+            ),
+            dominant_contributors,
+            truncated: false,
+            concentration,
+        })
+    }
+
+    ///Removes bot commits when bots are dropped, files changed by bots only are left out entirely
+    fn drop_bots(
+        histories: HashMap<String, Vec<String>>,
+        command: &BusFactorQueryCommand,
+    ) -> HashMap<String, Vec<String>> {
+        if command.bot_policy != BotPolicy::Drop {
+            return histories;
+        }
+        histories
+            .into_iter()
+            .filter_map(|(file, history)| {
+                if history.is_empty() {
+                    return Some((file, history));
+                }
+                let history: Vec<String> = history
+                    .into_iter()
+                    .filter(|author| !ContributorHandler::is_bot(author, false, command))
+                    .collect();
+                (!history.is_empty()).then_some((file, history))
+            })
+            .collect()
+    }
+
+    ///Degree of authorship of a developer over a file.
+    ///`first_authorship` - developer created the file,
+    ///`deliveries` - number of changes made by the developer,
+    ///`acceptances` - number of changes made by other developers.
+    fn degree_of_authorship(first_authorship: bool, deliveries: u32, acceptances: u32) -> f64 {
+        let first_authorship = if first_authorship { 1.0 } else { 0.0 };
+        DOA_BASE + DOA_FIRST_AUTHORSHIP * first_authorship + DOA_DELIVERIES * deliveries as f64
+            - DOA_ACCEPTANCES * (1.0 + acceptances as f64).ln()
+    }
+
+    ///Authors of a file given its commit history ordered from the newest to the oldest commit.
+    fn file_authors(history: &[String]) -> Vec<String> {
+        let Some(creator) = history.last() else {
+            return vec![];
+        };
+        let deliveries = history.iter().counts();
+        let doa: Vec<(&String, f64)> = deliveries
+            .iter()
+            .map(|(&developer, &count)| {
+                let acceptances = history.len() - count;
+                (
+                    developer,
+                    Self::degree_of_authorship(
+                        developer == creator,
+                        count as u32,
+                        acceptances as u32,
+                    ),
+                )
+            })
+            .collect();
+        let max_doa = doa.iter().map(|(_, doa)| *doa).fold(f64::MIN, f64::max);
+        doa.into_iter()
+            .filter(|(_, doa)| *doa >= DOA_BASE && doa / max_doa > DOA_NORMALIZED_THRESHOLD)
+            .map(|(developer, _)| developer.clone())
+            .sorted()
+            .collect()
+    }
+
+    ///Greedily removes authors with the highest number of authored files
+    ///until more than half of the files have no author left.
+    fn truck_factor(file_authors: &HashMap<String, Vec<String>>) -> Vec<String> {
+        let authored_files = file_authors
+            .values()
+            .flatten()
+            .counts()
+            .into_iter()
+            .sorted_by_key(|(author, count)| (Reverse(*count), *author))
+            .map(|(author, _)| author.clone())
+            .collect::<Vec<String>>();
+        let mut removed = HashSet::new();
+        let mut truck_factor = vec![];
+        for author in authored_files {
+            if Self::orphaned_files(file_authors, &removed) * 2 > file_authors.len() {
+                break;
+            }
+            removed.insert(author.clone());
+            truck_factor.push(author);
+        }
+        truck_factor
+    }
+
+    fn orphaned_files(
+        file_authors: &HashMap<String, Vec<String>>,
+        removed: &HashSet<String>,
+    ) -> usize {
+        file_authors
+            .values()
+            .filter(|authors| authors.iter().all(|author| removed.contains(author)))
+            .count()
+    }
+
+    ///Commit authors of every file in the tree, from the newest to the oldest commit.
+    ///Files whose history can not be read are skipped.
+    pub(crate) async fn fetch_file_histories(
+        data: &RepositoryQueryResult,
+    ) -> Result<HashMap<String, Vec<String>>> {
+        let files = Self::fetch_tree(data).await?;
+        log::debug!(
+            "Fetching history of {} files for {}",
+            files.len(),
+            data.project_name
+        );
+        let mut histories = HashMap::new();
+        let semaphore = Arc::new(Semaphore::new(CONCURRENT_FILE_REQUESTS));
+        let mut requests = JoinSet::new();
+        for file in files {
+            let data = data.clone();
+            let semaphore = semaphore.clone();
+            requests.spawn(async move {
+                let _permit = semaphore.acquire_owned().await?;
+                let history = Self::fetch_file_history(&data, &file).await;
+                Ok::<_, anyhow::Error>((file, history))
+            });
+        }
+        let mut skipped = 0;
+        while let Some(request) = requests.join_next().await {
+            let (file, history) = request??;
+            match history {
+                Ok(history) => {
+                    histories.insert(file, history);
+                }
+                Err(err) => {
+                    let reason = err.downcast::<NoDataReason>()?;
+                    log::warn!("Skipping {file} of {}: {reason}", data.project_name);
+                    skipped += 1;
+                }
+            }
+        }
+        if histories.is_empty() && skipped > 0 {
+            bail!(NoDataReason::AccessDenied);
+        }
+        Ok(histories)
+    }
+
+    async fn fetch_tree(data: &RepositoryQueryResult) -> Result<Vec<String>> {
+        if data.repository_url.is_empty() {
+            bail!("Missing repository URL for {}", data.project_name);
+        }
+        let full_url = Url::parse_with_params(
+            &format!("{}/git/trees/{}", data.repository_url, data.default_branch),
+            &[("recursive", "1")],
+        )
+        .map_err(|err| anyhow!(err))
+        .context("Could not create URL")?;
+        log::trace!("Targeting {:?}", &full_url);
+        let response = data.client_details.client.get(full_url).send().await?;
+        let response = ensure_contributor_data(response)
+            .await?
+            .json::<TreeResponse>()
+            .await?;
+        if response.truncated {
+            log::warn!("Tree of {} was truncated by GitHub", data.project_name);
+        }
+        let max_files = data.client_details.command.max_files as usize;
+        let files: Vec<String> = response
+            .tree
+            .into_iter()
+            .filter(|entry| entry.kind == "blob")
+            .map(|entry| entry.path)
+            .collect();
+        if files.len() > max_files {
+            log::warn!(
+                "{} has {} files, only first {max_files} are considered",
+                data.project_name,
+                files.len()
+            );
+        }
+        Ok(files.into_iter().take(max_files).collect())
+    }
+
+    ///Every page of the file history, the oldest commit names the creator of the file.
+    ///Commits within the `since` window only, when one is given.
+    async fn fetch_file_history(data: &RepositoryQueryResult, path: &str) -> Result<Vec<String>> {
+        let mut params = vec![
+            ("path", path.to_string()),
+            ("sha", data.default_branch.clone()),
+            ("per_page", COMMITS_PAGE_SIZE.to_string()),
+        ];
+        if let Some(window) = &data.client_details.command.since {
+            params.push(("since", window.start_iso()));
+        }
+        let mut next_url = Some(
+            Url::parse_with_params(&format!("{}/commits", data.repository_url), &params)
+                .map_err(|err| anyhow!(err))
+                .context("Could not create URL")?,
+        );
+        let aliases = &data.client_details.command.aliases;
+        let mut history = vec![];
+        while let Some(full_url) = next_url {
+            log::trace!("Targeting {:?}", &full_url);
+            let response = data.client_details.client.get(full_url).send().await?;
+            let response = ensure_contributor_data(response).await?;
+            next_url = next_page_url(response.headers());
+            history.extend(
+                response
+                    .json::<Vec<CommitDetails>>()
+                    .await?
+                    .into_iter()
+                    .map(CommitDetails::author)
+                    .map(|author| {
+                        aliases
+                            .canonical(&author)
+                            .map(str::to_string)
+                            .unwrap_or(author)
+                    }),
+            );
+        }
+        Ok(history)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct TreeResponse {
+    tree: Vec<TreeEntry>,
+    #[serde(default)]
+    truncated: bool,
+}
+#[derive(Deserialize, Debug)]
+struct TreeEntry {
+    path: String,
+    #[serde(rename = "type")]
+    kind: String,
+}
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)]
+struct CommitDetails {
+    commit: CommitInfo,
+    author: Option<CommitAuthor>,
+    #[serde(flatten)]
+    other: Other,
+}
+#[derive(Deserialize, Debug)]
+struct CommitInfo {
+    author: CommitSignature,
+}
+#[derive(Deserialize, Debug)]
+struct CommitSignature {
+    name: String,
+}
+#[derive(Deserialize, Debug)]
+struct CommitAuthor {
+    login: String,
+}
+impl CommitDetails {
+    ///GitHub login of the author, or the git author name for commits not linked to an account
+    fn author(self) -> String {
+        self.author
+            .map(|author| author.login)
+            .unwrap_or(self.commit.author.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn names(authors: &[&str]) -> Vec<String> {
+        authors.iter().map(|author| author.to_string()).collect()
+    }
+    #[test]
+    fn degree_of_authorship() {
+        let doa = TruckFactorHandler::degree_of_authorship(true, 1, 0);
+        assert!((doa - 4.555).abs() < 0.001);
+        let doa = TruckFactorHandler::degree_of_authorship(false, 0, 10);
+        assert!(doa < DOA_BASE);
+    }
+    #[test]
+    fn file_authors_creator_only() {
+        let authors = TruckFactorHandler::file_authors(&names(&["luke", "luke", "luke"]));
+        assert_eq!(authors, vec!["luke"]);
+    }
+    #[test]
+    fn file_authors_shared() {
+        let authors = TruckFactorHandler::file_authors(&names(&["kubot", "luke", "kubot", "luke"]));
+        assert_eq!(authors, vec!["luke"]);
+        let authors = TruckFactorHandler::file_authors(&names(&[
+            "kubot", "kubot", "kubot", "kubot", "kubot", "kubot", "kubot", "kubot", "kubot",
+            "kubot", "kubot", "kubot", "luke",
+        ]));
+        assert_eq!(authors, vec!["kubot"]);
+    }
+    #[test]
+    fn file_authors_empty() {
+        assert!(TruckFactorHandler::file_authors(&[]).is_empty());
+    }
+    #[test]
+    fn truck_factor() {
+        let file_authors = HashMap::from([
+            ("a.rs".to_string(), names(&["luke"])),
+            ("b.rs".to_string(), names(&["luke", "kubot"])),
+            ("c.rs".to_string(), names(&["kubot"])),
+            ("d.rs".to_string(), names(&["han"])),
+        ]);
+        let result = TruckFactorHandler::truck_factor(&file_authors);
+        assert_eq!(result, vec!["kubot", "luke"]);
+    }
+    #[test]
+    fn truck_factor_single_owner() {
+        let file_authors = HashMap::from([
+            ("a.rs".to_string(), names(&["luke"])),
+            ("b.rs".to_string(), names(&["luke"])),
+            ("c.rs".to_string(), names(&["kubot"])),
+        ]);
+        let result = TruckFactorHandler::truck_factor(&file_authors);
+        assert_eq!(result, vec!["luke"]);
+    }
+}
@@ -1,44 +1,168 @@
+use crate::query_handler::registry::UnresolvedDependency;
 use crate::query_handler::BusFactorQueryResult;
+use crate::{BusFactorQueryCommand, DetectionStrategy};
 use anyhow::Result;
 use itertools::Itertools;
-use std::sync::Arc;
+use std::cmp::Reverse;
 use tokio::sync::mpsc::Receiver;
-use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 
 pub(crate) struct ReportGenerator {}
-type DataStorage = Arc<Mutex<Vec<BusFactorQueryResult>>>;
 
 impl ReportGenerator {
-    pub(crate) async fn run(receiver: Receiver<BusFactorQueryResult>) -> Result<()> {
-        tokio::task::spawn(Self::process_results(receiver)).await??;
+    ///Results of every analysed project, available once every pipeline stage finished successfully
+    pub(crate) async fn collect(
+        receiver: Receiver<BusFactorQueryResult>,
+        stages: Vec<JoinHandle<Result<()>>>,
+    ) -> Result<Vec<BusFactorQueryResult>> {
+        let results = tokio::task::spawn(Self::process_results(receiver, stages)).await??;
         log::trace!("Closing Report Generator");
-        Ok(())
+        Ok(results)
     }
-    async fn process_results(mut receiver: Receiver<BusFactorQueryResult>) -> Result<()> {
+    async fn process_results(
+        mut receiver: Receiver<BusFactorQueryResult>,
+        stages: Vec<JoinHandle<Result<()>>>,
+    ) -> Result<Vec<BusFactorQueryResult>> {
         log::trace!("About to start consuming");
-        let final_data: DataStorage = Arc::new(Mutex::new(vec![]));
+        let mut results = vec![];
         while let Some(data) = receiver.recv().await {
             log::info!("we have data {data:?}");
-            Self::save_data(data, final_data.clone()).await;
+            results.push(data);
         }
         log::trace!("Closing Report Generator channel");
-        Self::print_data(final_data).await;
-        Ok(())
+        for stage in stages {
+            stage.await??;
+        }
+        Ok(results)
+    }
+    pub(crate) fn print(
+        results: &[BusFactorQueryResult],
+        command: &BusFactorQueryCommand,
+        unresolved: &[UnresolvedDependency],
+    ) {
+        Self::print_data(results, command);
+        Self::print_unresolved(unresolved);
+    }
+    fn print_data(data: &[BusFactorQueryResult], command: &BusFactorQueryCommand) {
+        println!(
+            "Bus factor: smallest group of top contributors covering {}% of {weight}. Dominant contributors: at least {}% of {weight}.",
+            command.coverage,
+            command.threshold,
+            weight = command.weight
+        );
+        if let Some(window) = &command.since {
+            println!("Only activity from the last {window} is considered.");
+        }
+        println!(
+            "Projects are flagged when {} reaches {}%.",
+            command.criterion, command.threshold
+        );
+        let grouped = command.languages.len() > 1;
+        //Languages are listed in the order they were requested
+        let language_order = |result: &BusFactorQueryResult| {
+            command
+                .languages
+                .iter()
+                .position(|language| language.trim() == result.language)
+        };
+        let sorted = data.iter().sorted_by(|left, right| {
+            language_order(left)
+                .cmp(&language_order(right))
+                .then_with(|| Self::rank(left, command).cmp(&Self::rank(right, command)))
+        });
+        for (language, results) in &sorted.group_by(|result| result.language.as_str()) {
+            if grouped {
+                println!("Language: {language}");
+            }
+            results.for_each(|print| Self::print_result(print, command));
+        }
     }
-    async fn save_data(data: BusFactorQueryResult, data_storage: DataStorage) {
-        let mut lock = data_storage.lock().await;
-        lock.push(data);
+    ///Dependencies which could not be analysed because their GitHub repository is unknown
+    fn print_unresolved(unresolved: &[UnresolvedDependency]) {
+        if unresolved.is_empty() {
+            return;
+        }
+        println!("Unresolved dependencies:");
+        for dependency in unresolved {
+            println!(
+                "dependency: {:20}\t\t{}\t\tunresolved: {}",
+                dependency.dependency.to_string(),
+                dependency.dependency.ecosystem,
+                dependency.reason
+            );
+        }
+    }
+    fn print_result(print: &BusFactorQueryResult, command: &BusFactorQueryCommand) {
+        let dependencies = if print.dependencies.is_empty() {
+            String::new()
+        } else {
+            format!("\t\tdependencies: {}", print.dependencies.iter().join(", "))
+        };
+        if let Some(reason) = print.no_data {
+            println!(
+                "project: {:20}\t\tstars: {:8}\t\tno contributor data: {reason}{dependencies}",
+                print.repo_name, print.stargazers
+            );
+            return;
+        }
+        //Truck factor measures authors by the files they authored
+        let unit = match command.strategy {
+            DetectionStrategy::Commits => command.weight.to_string(),
+            DetectionStrategy::TruckFactor => "files".to_string(),
+        };
+        let dominant: String = print
+            .dominant_contributors
+            .iter()
+            .map(|dominant| {
+                format!(
+                    "\t\tuser: {:20}\t\t{unit}: {:8}\t\tpercentage: {}",
+                    dominant.login, dominant.contributions, dominant.percentage
+                )
+            })
+            .collect();
+        let truncated = if print.truncated {
+            "\t\t(contributor list truncated)"
+        } else {
+            ""
+        };
+        let concentration = &print.concentration;
+        println!(
+            "{}project: {:20}\t\tstars: {:8}\t\tbus factor: {:4}\t\tkey contributors: {}{}\t\tgini: {:.2}\t\thhi: {:.4}\t\tentropy: {:.2}\t\ttop3: {:.0}%{}{}",
+            if print.flagged { "[!] " } else { "" },
+            print.repo_name,
+            print.stargazers,
+            print.bus_factor,
+            print.key_contributors.join(", "),
+            dominant,
+            concentration.gini,
+            concentration.hhi,
+            concentration.entropy,
+            100.0 * concentration.top3_share,
+            dependencies,
+            truncated
+        );
     }
-    async fn print_data(data_storage: Arc<Mutex<Vec<BusFactorQueryResult>>>) {
-        let data = data_storage.lock().await;
-        data.iter()
-            .sorted_unstable_by_key(|bus_factor| bus_factor.bus_factor)
-            .rev()
-            .for_each(|print| {
-                println!(
-                    "project: {:20}\t\tuser: {:20}\t\tpercentage: {}",
-                    print.repo_name, print.login, print.bus_factor
-                );
-            });
+    ///Flagged projects first, then the most concentrated according to the selected criterion,
+    ///projects without contributor data last
+    fn rank(
+        result: &BusFactorQueryResult,
+        command: &BusFactorQueryCommand,
+    ) -> (bool, Reverse<bool>, Reverse<u64>, u32, Reverse<Option<u32>>) {
+        let score = result
+            .concentration
+            .score(command.criterion)
+            .map_or(0, |score| (score * 100.0).round() as u64);
+        (
+            result.no_data.is_some(),
+            Reverse(result.flagged),
+            Reverse(score),
+            result.bus_factor,
+            Reverse(
+                result
+                    .dominant_contributors
+                    .first()
+                    .map(|dominant| dominant.percentage),
+            ),
+        )
     }
 }
@@ -1,7 +1,7 @@
 use assert_cmd::Command;
 use predicates::prelude::*;
 use serde_json::json;
-use wiremock::matchers::{method, path};
+use wiremock::matchers::{body_string_contains, header, method, path, query_param};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 #[tokio::test]
@@ -60,3 +60,1792 @@ async fn test_simple_scenario() {
             .and(predicate::str::contains("percentage: 100")),
     );
 }
+#[tokio::test]
+async fn test_threshold_out_of_range() {
+    Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["-l", "rust"])
+        .args(["-t", "101"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("threshold"));
+}
+#[tokio::test]
+async fn test_truck_factor_strategy() {
+    let mock_server = MockServer::start().await;
+    let repository_url = format!("{}/repos/test/test", &mock_server.uri());
+
+    Mock::given(method("GET"))
+        .and(path("/search/repositories"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            {
+                "total_count": 1,
+                "items": [
+                {
+                    "stargazers_count": 199,
+                    "contributors_url": format!("{repository_url}/contributors"),
+                    "url": repository_url,
+                    "default_branch": "main",
+                    "full_name": "test/test"
+                }
+            ]
+            }
+        )))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/test/test/git/trees/main"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            {
+                "tree": [
+                    {"path": "src", "type": "tree"},
+                    {"path": "src/a.rs", "type": "blob"},
+                    {"path": "src/b.rs", "type": "blob"},
+                    {"path": "README.md", "type": "blob"},
+                    {"path": "docs/private.md", "type": "blob"}
+                ],
+                "truncated": false
+            }
+        )))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    for (file, author) in [
+        ("src/a.rs", "test_user"),
+        ("src/b.rs", "test_user"),
+        ("README.md", "other_user"),
+    ] {
+        Mock::given(method("GET"))
+            .and(path("/repos/test/test/commits"))
+            .and(query_param("path", file))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+                [
+                    {
+                        "author": {"login": author},
+                        "commit": {"author": {"name": author}}
+                    }
+                ]
+            )))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+    }
+    //Unreadable history skips the file only
+    Mock::given(method("GET"))
+        .and(path("/repos/test/test/commits"))
+        .and(query_param("path", "docs/private.md"))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let url = format!("{}/search/repositories", &mock_server.uri());
+
+    Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["-l", "rust"])
+        .args(["-p", "1"])
+        .args(["-g", &url])
+        .args(["-s", "truck-factor"])
+        .args(["--threshold", "60"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("[!] project: test/test")
+                .and(predicate::str::contains("bus factor:    1"))
+                .and(predicate::str::contains("key contributors: test_user"))
+                .and(
+                    predicate::str::is_match(r"user: test_user\s+files:\s+2\s+percentage: 66")
+                        .unwrap(),
+                ),
+        );
+}
+#[tokio::test]
+async fn test_truck_factor_file_history_pagination() {
+    let mock_server = MockServer::start().await;
+    let repository_url = format!("{}/repos/test/test", &mock_server.uri());
+
+    Mock::given(method("GET"))
+        .and(path("/search/repositories"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            {
+                "total_count": 1,
+                "items": [
+                {
+                    "stargazers_count": 199,
+                    "contributors_url": format!("{repository_url}/contributors"),
+                    "url": repository_url,
+                    "default_branch": "main",
+                    "full_name": "test/test"
+                }
+            ]
+            }
+        )))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/test/test/git/trees/main"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            {"tree": [{"path": "src/lib.rs", "type": "blob"}], "truncated": false}
+        )))
+        .mount(&mock_server)
+        .await;
+    //Oldest commit, naming the creator of the file, is on the second page
+    Mock::given(method("GET"))
+        .and(path("/repos/test/test/commits"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            [{"author": {"login": "creator"}, "commit": {"author": {"name": "creator"}}}]
+        )))
+        .with_priority(1)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/test/test/commits"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header(
+                    "link",
+                    format!(r#"<{repository_url}/commits?path=src%2Flib.rs&page=2>; rel="next""#)
+                        .as_str(),
+                )
+                .set_body_json(json!(
+                    [{"author": {"login": "maintainer"}, "commit": {"author": {"name": "maintainer"}}}]
+                )),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let url = format!("{}/search/repositories", &mock_server.uri());
+
+    Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["-l", "rust"])
+        .args(["-p", "1"])
+        .args(["-g", &url])
+        .args(["-s", "truck-factor"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("bus factor:    1")
+                .and(predicate::str::contains("key contributors: creator")),
+        );
+}
+#[tokio::test]
+async fn test_contributor_pagination() {
+    let mock_server = MockServer::start().await;
+    let contributor_path = format!("{}/repos/test/test/contributors", &mock_server.uri());
+
+    Mock::given(method("GET"))
+        .and(path("/search/repositories"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            {
+                "total_count": 1,
+                "items": [
+                {
+                    "stargazers_count": 199,
+                    "contributors_url": contributor_path,
+                    "full_name": "test/test"
+                }
+            ]
+            }
+        )))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/test/test/contributors"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            [{"login": "second_page_user", "contributions": 300}]
+        )))
+        .with_priority(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/test/test/contributors"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header(
+                    "link",
+                    format!(r#"<{contributor_path}?page=2>; rel="next", <{contributor_path}?page=2>; rel="last""#)
+                        .as_str(),
+                )
+                .set_body_json(json!(
+                    [{"login": "first_page_user", "contributions": 100}]
+                )),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let url = format!("{}/search/repositories", &mock_server.uri());
+
+    Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["-l", "rust"])
+        .args(["-p", "1"])
+        .args(["-g", &url])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("user: second_page_user")
+                .and(predicate::str::contains("percentage: 75"))
+                .and(predicate::str::contains("truncated").not()),
+        );
+
+    Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["-l", "rust"])
+        .args(["-p", "1"])
+        .args(["-g", &url])
+        .args(["--max-contributor-pages", "1"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("user: first_page_user")
+                .and(predicate::str::contains("(contributor list truncated)")),
+        );
+}
+#[tokio::test]
+async fn test_since_window_with_computing_statistics() {
+    let mock_server = MockServer::start().await;
+    let repository_url = format!("{}/repos/test/test", &mock_server.uri());
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let last_week = now - 7 * 24 * 60 * 60;
+    let years_ago = now - 3 * 365 * 24 * 60 * 60;
+
+    Mock::given(method("GET"))
+        .and(path("/search/repositories"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            {
+                "total_count": 1,
+                "items": [
+                {
+                    "stargazers_count": 199,
+                    "contributors_url": format!("{repository_url}/contributors"),
+                    "url": repository_url,
+                    "full_name": "test/test"
+                }
+            ]
+            }
+        )))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/test/test/stats/contributors"))
+        .respond_with(ResponseTemplate::new(202).set_body_json(json!({})))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/test/test/stats/contributors"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            [
+                {
+                    "author": {"login": "founder", "type": "User"},
+                    "total": 1000,
+                    "weeks": [{"w": years_ago, "a": 10000, "d": 0, "c": 1000}]
+                },
+                {
+                    "author": {"login": "maintainer", "type": "User"},
+                    "total": 60,
+                    "weeks": [
+                        {"w": years_ago, "a": 100, "d": 0, "c": 10},
+                        {"w": last_week, "a": 500, "d": 50, "c": 50}
+                    ]
+                }
+            ]
+        )))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let url = format!("{}/search/repositories", &mock_server.uri());
+
+    Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["-l", "rust"])
+        .args(["-p", "1"])
+        .args(["-g", &url])
+        .args(["--since", "12m"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("last 12m")
+                .and(predicate::str::contains("user: maintainer"))
+                .and(predicate::str::contains("percentage: 100")),
+        );
+}
+#[tokio::test]
+async fn test_no_contributor_data() {
+    let mock_server = MockServer::start().await;
+    let uri = mock_server.uri();
+
+    Mock::given(method("GET"))
+        .and(path("/search/repositories"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            {
+                "total_count": 5,
+                "items": [
+                    {"stargazers_count": 40, "contributors_url": format!("{uri}/repos/test/empty/contributors"), "full_name": "test/empty"},
+                    {"stargazers_count": 35, "contributors_url": format!("{uri}/repos/test/robots/contributors"), "full_name": "test/robots"},
+                    {"stargazers_count": 30, "contributors_url": format!("{uri}/repos/test/blank/contributors"), "full_name": "test/blank"},
+                    {"stargazers_count": 20, "contributors_url": format!("{uri}/repos/test/huge/contributors"), "full_name": "test/huge"},
+                    {"stargazers_count": 10, "contributors_url": format!("{uri}/repos/test/private/contributors"), "full_name": "test/private"}
+                ]
+            }
+        )))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/test/empty/contributors"))
+        .respond_with(ResponseTemplate::new(204))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/test/robots/contributors"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {"login": "dependabot[bot]", "type": "Bot", "contributions": 50},
+            {"login": "renovate[bot]", "type": "Bot", "contributions": 20}
+        ])))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/test/blank/contributors"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/test/huge/contributors"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+            "message": "The history or contributor list is too large to list contributors for this repository via the API."
+        })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/test/private/contributors"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+            "message": "Repository access blocked"
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let url = format!("{uri}/search/repositories");
+
+    Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["-l", "rust"])
+        .args(["-p", "5"])
+        .args(["-g", &url])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::is_match(r"test/empty.*no contributor data: empty repository")
+                .unwrap()
+                .and(
+                    predicate::str::is_match(
+                        r"test/robots.*no contributor data: only bot contributors",
+                    )
+                    .unwrap(),
+                )
+                .and(
+                    predicate::str::is_match(r"test/blank.*no contributor data: empty repository")
+                        .unwrap(),
+                )
+                .and(
+                    predicate::str::is_match(
+                        r"test/huge.*no contributor data: contributor list too large",
+                    )
+                    .unwrap(),
+                )
+                .and(
+                    predicate::str::is_match(r"test/private.*no contributor data: access denied")
+                        .unwrap(),
+                ),
+        );
+}
+#[tokio::test]
+async fn test_multiple_languages() {
+    let mock_server = MockServer::start().await;
+    let contributor_path = |name: &str| format!("{}/repos/{name}/contributors", &mock_server.uri());
+
+    Mock::given(method("GET"))
+        .and(path("/search/repositories"))
+        .and(query_param("q", "language:rust"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            {
+                "total_count": 2,
+                "items": [
+                    {
+                        "stargazers_count": 300,
+                        "contributors_url": contributor_path("ferris"),
+                        "full_name": "ferris"
+                    },
+                    {
+                        "stargazers_count": 200,
+                        "contributors_url": contributor_path("bindings"),
+                        "full_name": "bindings"
+                    }
+                ]
+            }
+        )))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/search/repositories"))
+        .and(query_param("q", "language:go"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            {
+                "total_count": 2,
+                "items": [
+                    {
+                        "stargazers_count": 250,
+                        "contributors_url": contributor_path("gopher"),
+                        "full_name": "gopher"
+                    },
+                    {
+                        "stargazers_count": 200,
+                        "contributors_url": contributor_path("bindings"),
+                        "full_name": "bindings"
+                    }
+                ]
+            }
+        )))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    for name in ["ferris", "gopher", "bindings"] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/{name}/contributors")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+                [{"login": format!("{name}_user"), "contributions": 10}]
+            )))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+    }
+
+    let url = format!("{}/search/repositories", &mock_server.uri());
+
+    let cli = Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["-l", "rust,go"])
+        .args(["-p", "2"])
+        .args(["-g", &url])
+        .assert();
+
+    cli.success().stdout(
+        predicate::str::is_match(
+            "Language: rust\n(?:[^\n]*project: (?:ferris|bindings) [^\n]*\n){2}Language: go\n[^\n]*project: gopher [^\n]*\n$",
+        )
+        .unwrap(),
+    );
+}
+#[tokio::test]
+async fn test_multiple_languages_keep_first_language() {
+    let mock_server = MockServer::start().await;
+    let contributor_path = format!("{}/repos/shared/contributors", &mock_server.uri());
+    let project = |name: String| {
+        json!({
+            "stargazers_count": 100,
+            "contributors_url": contributor_path,
+            "full_name": name
+        })
+    };
+    let rust_projects: Vec<_> = (0..30).map(|id| project(format!("ferris-{id}"))).collect();
+
+    Mock::given(method("GET"))
+        .and(path("/search/repositories"))
+        .and(query_param("q", "language:rust"))
+        .and(query_param("page", "1"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"total_count": 31, "items": rust_projects})),
+        )
+        .mount(&mock_server)
+        .await;
+    //Second rust page is slow, the go search must still wait for it
+    Mock::given(method("GET"))
+        .and(path("/search/repositories"))
+        .and(query_param("q", "language:rust"))
+        .and(query_param("page", "2"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(
+                    json!({"total_count": 31, "items": [project("bindings".to_string())]}),
+                )
+                .set_delay(std::time::Duration::from_millis(500)),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/search/repositories"))
+        .and(query_param("q", "language:go"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(
+                json!({"total_count": 1, "items": [project("bindings".to_string())]}),
+            ),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/shared/contributors"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!([{"login": "shared_user", "contributions": 10}])),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let url = format!("{}/search/repositories", &mock_server.uri());
+
+    let cli = Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["-l", "rust,go"])
+        .args(["-p", "31"])
+        .args(["-g", &url])
+        .assert();
+
+    cli.success().stdout(
+        predicate::str::is_match("Language: rust\n(?:[^\n]*\n)*[^\n]*project: bindings ")
+            .unwrap()
+            .and(predicate::str::contains("Language: go").not()),
+    );
+}
+#[tokio::test]
+async fn test_star_range_partitioning() {
+    let mock_server = MockServer::start().await;
+    let contributor_path = format!("{}/repos/shared/contributors", &mock_server.uri());
+    let projects = |stars: u32, from: u32, count: u32| {
+        (from..from + count)
+            .map(|id| {
+                json!({
+                    "stargazers_count": stars,
+                    "contributors_url": contributor_path,
+                    "full_name": format!("project-{id}")
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+    let search = |query: &str, page: &str, per_page: &str| {
+        Mock::given(method("GET"))
+            .and(path("/search/repositories"))
+            .and(query_param("q", query))
+            .and(query_param("page", page))
+            .and(query_param("per_page", per_page))
+    };
+
+    search("language:rust", "1", "30")
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            {"total_count": 3000, "items": projects(10, 0, 30)}
+        )))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    for (query, total_count) in [
+        ("language:rust stars:0..10", 3000),
+        ("language:rust stars:6..10", 800),
+        ("language:rust stars:0..5", 2200),
+        ("language:rust stars:3..5", 600),
+    ] {
+        search(query, "1", "1")
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+                {"total_count": total_count, "items": []}
+            )))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+    }
+    //Secondary rate limit on one page is waited out, pages are still sent in star order
+    search("language:rust stars:6..10", "3", "100")
+        .respond_with(ResponseTemplate::new(403).insert_header("retry-after", "1"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    for page in 1..=8 {
+        search("language:rust stars:6..10", &page.to_string(), "100")
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+                {"total_count": 800, "items": projects(10 - page / 2, (page - 1) * 100, 100)}
+            )))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+    }
+    for page in 1..=3 {
+        search("language:rust stars:3..5", &page.to_string(), "100")
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+                {"total_count": 600, "items": projects(5, 700 + page * 100, 100)}
+            )))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+    }
+    Mock::given(method("GET"))
+        .and(path("/repos/shared/contributors"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            [{"login": "test_user", "contributions": 10}]
+        )))
+        .expect(1001)
+        .mount(&mock_server)
+        .await;
+
+    let url = format!("{}/search/repositories", &mock_server.uri());
+
+    let cli = Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["-l", "rust"])
+        .args(["-p", "1001"])
+        .args(["-g", &url])
+        .assert();
+
+    let output = cli.success().get_output().stdout.clone();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.matches("project: ").count(), 1001);
+    assert!(output.contains("project: project-1000 "));
+    assert!(!output.contains("project: project-1001 "));
+}
+#[tokio::test]
+async fn test_explicit_repositories() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/search/repositories"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+    for (name, stars) in [("rebels/falcon", 120), ("empire/star", 80)] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/{name}")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+                {
+                    "stargazers_count": stars,
+                    "contributors_url": format!("{}/repos/{name}/contributors", &mock_server.uri()),
+                    "full_name": name,
+                    "language": "Rust"
+                }
+            )))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/{name}/contributors")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+                [{"login": "test_user", "contributions": 10}]
+            )))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+    }
+    //Unreadable repositories are reported one by one, names are percent-encoded
+    for (repository_path, status) in [
+        ("/repos/empire/vault", 403),
+        ("/repos/empire/reactor", 502),
+        ("/repos/empire/plans%23v2", 404),
+    ] {
+        Mock::given(method("GET"))
+            .and(path(repository_path))
+            .respond_with(ResponseTemplate::new(status))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+    }
+
+    let url = format!("{}/search/repositories", &mock_server.uri());
+
+    let cli = Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args([
+            "--repos",
+            "empire/star,empire/vault,empire/reactor,empire/plans#v2",
+        ])
+        .args(["--repos-file", "tests/fixtures/repos.txt"])
+        .args(["-g", &url])
+        .assert();
+
+    cli.success().stdout(
+        predicate::str::contains("project: rebels/falcon")
+            .and(predicate::str::contains("stars:      120"))
+            .and(predicate::str::contains("project: empire/star"))
+            .and(
+                predicate::str::is_match(
+                    r"project: empire/vault\s.*no contributor data: access denied",
+                )
+                .unwrap(),
+            )
+            .and(
+                predicate::str::is_match(
+                    r"project: empire/reactor\s.*no contributor data: forge failed to answer",
+                )
+                .unwrap(),
+            )
+            .and(
+                predicate::str::is_match(
+                    r"project: empire/plans#v2\s.*no contributor data: repository not found",
+                )
+                .unwrap(),
+            ),
+    );
+}
+#[tokio::test]
+async fn test_page_limits_out_of_range() {
+    for limit in ["--max-contributor-pages", "--max-files"] {
+        Command::cargo_bin("bus_factor")
+            .unwrap()
+            .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+            .args(["-l", "rust"])
+            .args([limit, "0"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(limit));
+    }
+}
+#[tokio::test]
+async fn test_truck_factor_rejects_line_weight() {
+    Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["-l", "rust"])
+        .args(["--strategy", "truck-factor", "--weight", "churn"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("truck-factor"));
+}
+#[tokio::test]
+async fn test_gitlab_rejects_line_weight() {
+    Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITLAB_ACCESS_TOKEN", "GITLAB_PAT")
+        .args(["--provider", "gitlab", "-l", "go"])
+        .args(["--weight", "additions"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "GitLab provider does not support --weight additions",
+        ));
+}
+#[tokio::test]
+async fn test_invalid_repository_name() {
+    Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["--repos", "rebels"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("owner/name"));
+}
+#[tokio::test]
+async fn test_repositories_conflict_with_subcommand() {
+    Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["--repos", "rebels/x-wing", "org", "rebels"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "can not be used with a subcommand",
+        ));
+}
+#[tokio::test]
+async fn test_organization_scan() {
+    let mock_server = MockServer::start().await;
+    let repos_path = format!("{}/orgs/rebels/repos", &mock_server.uri());
+    let project = |name: &str, stars: u32, fork: bool, archived: bool| {
+        json!({
+            "stargazers_count": stars,
+            "contributors_url": format!("{}/repos/rebels/{name}/contributors", &mock_server.uri()),
+            "full_name": format!("rebels/{name}"),
+            "fork": fork,
+            "archived": archived
+        })
+    };
+
+    Mock::given(method("GET"))
+        .and(path("/orgs/rebels/repos"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            project("xwing", 40, false, false),
+            project("old-base", 90, false, true)
+        ])))
+        .expect(1)
+        .with_priority(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/orgs/rebels/repos"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header(
+                    "link",
+                    format!(
+                        r#"<{repos_path}?page=2>; rel="next", <{repos_path}?page=2>; rel="last""#
+                    )
+                    .as_str(),
+                )
+                .set_body_json(json!([
+                    project("falcon", 120, false, false),
+                    project("falcon-fork", 80, true, false),
+                    project("droid", 2, false, false)
+                ])),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    for name in ["falcon", "xwing"] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/rebels/{name}/contributors")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+                [{"login": "test_user", "contributions": 10}]
+            )))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+    }
+
+    let url = format!("{}/search/repositories", &mock_server.uri());
+
+    let cli = Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["-g", &url])
+        .args(["org", "rebels", "--min-stars", "10"])
+        .assert();
+
+    let output = cli.success().get_output().stdout.clone();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.matches("project: ").count(), 2);
+    assert!(output.contains("project: rebels/falcon "));
+    assert!(output.contains("project: rebels/xwing "));
+}
+#[tokio::test]
+async fn test_cargo_lock_dependencies() {
+    let mock_server = MockServer::start().await;
+    let registry_url = format!("{}/registry/api/v1", &mock_server.uri());
+
+    for (name, repository) in [
+        ("falcon", json!("https://github.com/rebels/falcon")),
+        (
+            "falcon-derive",
+            json!("https://github.com/rebels/falcon/tree/main/derive"),
+        ),
+        ("nav-computer", json!(null)),
+    ] {
+        Mock::given(method("GET"))
+            .and(path(format!("/registry/api/v1/crates/{name}")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+                {"crate": {"name": name, "repository": repository}}
+            )))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+    }
+    Mock::given(method("GET"))
+        .and(path("/registry/api/v1/crates/hyperdrive"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+    for (name, stars) in [("rebels/falcon", 500), ("rebels/hyperdrive", 20)] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/{name}")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+                {
+                    "stargazers_count": stars,
+                    "contributors_url": format!("{}/repos/{name}/contributors", &mock_server.uri()),
+                    "full_name": name
+                }
+            )))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/{name}/contributors")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+                [{"login": "test_user", "contributions": 10}]
+            )))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+    }
+
+    let url = format!("{}/search/repositories", &mock_server.uri());
+
+    let cli = Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["-g", &url])
+        .args(["deps", "tests/fixtures/cargo/Cargo.lock"])
+        .args(["--registry-url", &registry_url])
+        .assert();
+
+    cli.success().stdout(
+        predicate::str::contains("dependencies: falcon (direct), falcon-derive (transitive)")
+            .and(predicate::str::contains(
+                "dependencies: hyperdrive (direct)",
+            ))
+            .and(predicate::str::is_match(
+            "Unresolved dependencies:\ndependency: nav-computer \\(transitive\\)\\s+cargo\t\tunresolved: no repository in package metadata\n$",
+        )
+        .unwrap()),
+    );
+}
+#[tokio::test]
+async fn test_npm_and_pypi_dependencies() {
+    let mock_server = MockServer::start().await;
+    let npm_url = format!("{}/npm", &mock_server.uri());
+    let pypi_url = format!("{}/pypi-mirror", &mock_server.uri());
+
+    for (registry_path, body) in [
+        (
+            "/npm/@rebels%2ffalcon/latest",
+            json!({"repository": {"type": "git", "url": "git+https://github.com/rebels/falcon.git"}}),
+        ),
+        (
+            "/npm/hyperdrive/latest",
+            json!({"repository": "rebels/falcon"}),
+        ),
+        (
+            "/pypi-mirror/pypi/nav-computer/json",
+            json!({"info": {"project_urls": {"Source": "https://gitlab.com/rebels/nav"}, "home_page": null}}),
+        ),
+    ] {
+        Mock::given(method("GET"))
+            .and(path(registry_path))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+    }
+    Mock::given(method("GET"))
+        .and(path("/pypi-mirror/pypi/imperial-probe/json"))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    //Failing lookup is reported for the package, the scan goes on
+    Mock::given(method("GET"))
+        .and(path("/pypi-mirror/pypi/droid-firmware/json"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/rebels/falcon"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            {
+                "stargazers_count": 500,
+                "contributors_url": format!("{}/repos/rebels/falcon/contributors", &mock_server.uri()),
+                "full_name": "rebels/falcon"
+            }
+        )))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/rebels/falcon/contributors"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            [{"login": "test_user", "contributions": 10}]
+        )))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let url = format!("{}/search/repositories", &mock_server.uri());
+
+    let cli = Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["-g", &url])
+        .args([
+            "deps",
+            "tests/fixtures/npm/package-lock.json",
+            "tests/fixtures/python/requirements.txt",
+        ])
+        .args(["--npm-registry-url", &npm_url])
+        .args(["--pypi-registry-url", &pypi_url])
+        .assert();
+
+    cli.success().stdout(
+        predicate::str::contains(
+            "project: rebels/falcon",
+        )
+        .and(predicate::str::contains(
+            "dependencies: @rebels/falcon (direct), hyperdrive (transitive)",
+        ))
+        .and(predicate::str::contains(
+            "dependency: imperial-probe (direct)\t\tpypi\t\tunresolved: not published in the registry",
+        ))
+        .and(predicate::str::contains(
+            "dependency: droid-firmware (direct)\t\tpypi\t\tunresolved: registry lookup failed: HTTP status server error (503 Service Unavailable)",
+        ))
+        .and(predicate::str::contains(
+            "dependency: nav-computer (direct)\t\tpypi\t\tunresolved: repository https://gitlab.com/rebels/nav is not on GitHub",
+        )),
+    );
+}
+
+#[tokio::test]
+async fn test_cyclonedx_sbom() {
+    let mock_server = MockServer::start().await;
+    let npm_url = format!("{}/npm", &mock_server.uri());
+
+    Mock::given(method("GET"))
+        .and(path("/npm/hyperdrive/latest"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({"repository": "rebels/falcon"})),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/rebels/falcon"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            {
+                "stargazers_count": 500,
+                "contributors_url": format!("{}/repos/rebels/falcon/contributors", &mock_server.uri()),
+                "full_name": "rebels/falcon"
+            }
+        )))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/rebels/falcon/contributors"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            [{"login": "test_user", "contributions": 10}]
+        )))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let url = format!("{}/search/repositories", &mock_server.uri());
+
+    let cli = Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["-g", &url])
+        .args(["deps", "tests/fixtures/sbom/bom.cdx.json"])
+        .args(["--npm-registry-url", &npm_url])
+        .assert();
+
+    cli.success().stdout(
+        predicate::str::contains("project: rebels/falcon")
+            .and(predicate::str::contains(
+                "dependencies: falcon (direct) [pkg:cargo/falcon@1.0.0], hyperdrive (transitive) [pkg:npm/hyperdrive@2.0.0]",
+            ))
+            .and(predicate::str::contains(
+                "dependency: death-star-plans (direct) [pkg:generic/death-star-plans@4]\t\tunknown\t\tunresolved: ecosystem is not supported",
+            )),
+    );
+}
+
+///Creates a fresh git repository with one commit per `(author, file)` pair, oldest first
+fn git_fixture(name: &str, commits: &[(&str, &str)]) -> std::path::PathBuf {
+    let commits: Vec<_> = commits
+        .iter()
+        .map(|(author, file)| (*author, *file, None))
+        .collect();
+    dated_git_fixture(name, &commits)
+}
+
+///Commits with a date are backdated, the others are committed now
+fn dated_git_fixture(name: &str, commits: &[(&str, &str, Option<&str>)]) -> std::path::PathBuf {
+    let repository = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&repository);
+    std::fs::create_dir_all(&repository).unwrap();
+    let git = |args: &[&str], date: Option<&str>| {
+        let mut command = std::process::Command::new("git");
+        command
+            .arg("-C")
+            .arg(&repository)
+            .args(args)
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("GIT_AUTHOR_NAME", "ci")
+            .env("GIT_AUTHOR_EMAIL", "ci@example.org")
+            .env("GIT_COMMITTER_NAME", "ci")
+            .env("GIT_COMMITTER_EMAIL", "ci@example.org");
+        if let Some(date) = date {
+            command
+                .env("GIT_AUTHOR_DATE", date)
+                .env("GIT_COMMITTER_DATE", date);
+        }
+        assert!(command.status().unwrap().success());
+    };
+    git(&["init", "--quiet"], None);
+    for (number, (author, file, date)) in commits.iter().enumerate() {
+        let content = format!("change {number}\n");
+        let path = repository.join(file);
+        let previous = std::fs::read_to_string(&path).unwrap_or_default();
+        std::fs::write(&path, previous + &content).unwrap();
+        git(&["add", file], None);
+        git(
+            &[
+                "commit",
+                "--quiet",
+                "--author",
+                &format!("{author} <{}@rebels.org>", author.to_lowercase()),
+                "-m",
+                &format!("Change {number}"),
+            ],
+            *date,
+        );
+    }
+    repository
+}
+
+#[test]
+fn test_local_repositories() {
+    let solo = git_fixture(
+        "local-solo",
+        &[
+            ("Luke", "x-wing.rs"),
+            ("Luke", "x-wing.rs"),
+            ("Luke", "lightsaber.rs"),
+            ("Leia", "x-wing.rs"),
+        ],
+    );
+    let empty = git_fixture("local-empty", &[]);
+
+    let cli = Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env_remove("GITHUB_ACCESS_TOKEN")
+        .arg("local")
+        .arg(&solo)
+        .arg(&empty)
+        .assert();
+
+    cli.success().stdout(
+        predicate::str::is_match(
+            r"\[!\] project: local-solo\s+stars:\s+0\s+bus factor:\s+1\s+key contributors: Luke\s+user: Luke\s+commits:\s+3\s+percentage: 75",
+        )
+        .unwrap()
+        .and(predicate::str::contains(
+            "no contributor data: empty repository",
+        )),
+    );
+}
+
+#[test]
+fn test_local_repository_truck_factor() {
+    let repository = git_fixture(
+        "local-truck-factor",
+        &[
+            ("Luke", "x-wing.rs"),
+            ("Leia", "blaster.rs"),
+            ("Han", "falcon.rs"),
+            ("Han", "hyperdrive.rs"),
+        ],
+    );
+
+    let cli = Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env_remove("GITHUB_ACCESS_TOKEN")
+        .args(["--strategy", "truck-factor"])
+        .arg("local")
+        .arg(&repository)
+        .assert();
+
+    cli.success().stdout(
+        predicate::str::is_match(
+            r"project: local-truck-factor\s+stars:\s+0\s+bus factor:\s+2\s+key contributors: Han, ",
+        )
+        .unwrap(),
+    );
+}
+
+#[test]
+fn test_local_repository_truck_factor_since() {
+    let old = Some("2001-01-01T00:00:00+00:00");
+    let repository = dated_git_fixture(
+        "local-truck-factor-since",
+        &[
+            ("Obi-Wan", "lightsaber.rs", old),
+            ("Obi-Wan", "robe.rs", old),
+            ("Luke", "lightsaber.rs", None),
+            ("Luke", "robe.rs", None),
+            ("Luke", "robe.rs", None),
+        ],
+    );
+
+    let cli = Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env_remove("GITHUB_ACCESS_TOKEN")
+        .args(["--strategy", "truck-factor", "--since", "1y"])
+        .arg("local")
+        .arg(&repository)
+        .assert();
+
+    cli.success()
+        .stdout(
+            predicate::str::is_match(
+                r"project: local-truck-factor-since\s+stars:\s+0\s+bus factor:\s+1\s+key contributors: Luke\s",
+            )
+            .unwrap(),
+        )
+        .stdout(predicate::str::contains("Obi-Wan").not());
+}
+
+#[test]
+fn test_local_repository_truck_factor_bots() {
+    let repository = git_fixture(
+        "local-truck-factor-bots",
+        &[
+            ("dependabot[bot]", "Cargo.lock"),
+            ("dependabot[bot]", "package-lock.json"),
+            ("dependabot[bot]", "yarn.lock"),
+            ("Luke", "x-wing.rs"),
+            ("Leia", "blaster.rs"),
+        ],
+    );
+    let analyse = |args: &[&str]| {
+        Command::cargo_bin("bus_factor")
+            .unwrap()
+            .env_remove("GITHUB_ACCESS_TOKEN")
+            .args(["--strategy", "truck-factor", "--threshold", "50"])
+            .args(args)
+            .arg("local")
+            .arg(&repository)
+            .assert()
+            .success()
+    };
+
+    //Dropped bots author nothing, files changed by bots only are left out
+    analyse(&[]).stdout(
+        predicate::str::is_match(r"bus factor:\s+2\s+key contributors: Leia, Luke\s")
+            .unwrap()
+            .and(predicate::str::contains("dependabot").not()),
+    );
+    //Kept for the truck factor, but never reported as dominant
+    analyse(&["--bots", "exclude-from-dominant"]).stdout(
+        predicate::str::is_match(r"bus factor:\s+1\s+key contributors: dependabot\[bot\]\s")
+            .unwrap()
+            .and(predicate::str::contains("user: dependabot").not())
+            .and(predicate::str::contains("[!]").not()),
+    );
+}
+
+#[tokio::test]
+async fn test_gitlab_provider() {
+    let mock_server = MockServer::start().await;
+    let api_url = format!("{}/api/v4", &mock_server.uri());
+
+    Mock::given(method("GET"))
+        .and(path("/api/v4/projects"))
+        .and(query_param("with_programming_language", "Go"))
+        .and(query_param("order_by", "star_count"))
+        .and(query_param("sort", "desc"))
+        .and(header("PRIVATE-TOKEN", "GITLAB_PAT"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header(
+                    "link",
+                    format!(r#"<{api_url}/projects?order_by=star_count&page=2>; rel="next""#)
+                        .as_str(),
+                )
+                .set_body_json(json!([
+                    {"id": 1, "path_with_namespace": "rebels/falcon", "star_count": 500, "default_branch": "main"},
+                    {"id": 2, "path_with_namespace": "rebels/fleet/x-wing", "star_count": 300}
+                ])),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v4/projects"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {"id": 3, "path_with_namespace": "empire/death-star", "star_count": 10}
+        ])))
+        .with_priority(1)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    for (project, contributors) in [
+        (
+            1,
+            json!([
+                {"name": "Han", "email": "han@rebels.org", "commits": 90, "additions": 0, "deletions": 0},
+                {"name": "Chewie", "email": "chewie@rebels.org", "commits": 10, "additions": 0, "deletions": 0}
+            ]),
+        ),
+        (
+            2,
+            json!([
+                {"name": "Luke", "email": "luke@rebels.org", "commits": 50},
+                {"name": "Wedge", "email": "wedge@rebels.org", "commits": 50}
+            ]),
+        ),
+        (3, json!([])),
+    ] {
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/api/v4/projects/{project}/repository/contributors"
+            )))
+            .and(query_param("order_by", "commits"))
+            .and(header("PRIVATE-TOKEN", "GITLAB_PAT"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(contributors))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+    }
+
+    let cli = Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env_remove("GITHUB_ACCESS_TOKEN")
+        .env("GITLAB_ACCESS_TOKEN", "GITLAB_PAT")
+        .args(["--provider", "gitlab", "--gitlab-url", &api_url])
+        .args(["-l", "Go", "-p", "3"])
+        .assert();
+
+    cli.success().stdout(
+        predicate::str::is_match(
+            r"\[!\] project: rebels/falcon\s+stars:\s+500\s+bus factor:\s+1\s+key contributors: Han\s+user: Han\s+commits:\s+90\s+percentage: 90",
+        )
+        .unwrap()
+        .and(
+            predicate::str::is_match(
+                r"project: rebels/fleet/x-wing\s+stars:\s+300\s+bus factor:\s+1\s+key contributors: (Luke|Wedge)\s",
+            )
+            .unwrap(),
+        )
+        .and(predicate::str::contains(
+            "project: empire/death-star",
+        ))
+        .and(predicate::str::contains("no contributor data: empty repository")),
+    );
+}
+
+#[tokio::test]
+async fn test_gitea_provider() {
+    let mock_server = MockServer::start().await;
+    let api_url = format!("{}/api/v1", &mock_server.uri());
+
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/search"))
+        .and(query_param("sort", "stars"))
+        .and(query_param("order", "desc"))
+        .and(header("Authorization", "token GITEA_PAT"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header(
+                    "link",
+                    format!(r#"<{api_url}/repos/search?sort=stars&order=desc&page=2>; rel="next""#)
+                        .as_str(),
+                )
+                .set_body_json(json!({"ok": true, "data": [
+                    {"full_name": "forgejo/runner", "stars_count": 80, "language": "Go", "default_branch": "main"},
+                    {"full_name": "forgejo/docs", "stars_count": 70, "language": "Markdown", "default_branch": "main"}
+                ]})),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/search"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ok": true, "data": [
+            {"full_name": "forgejo/act", "stars_count": 30, "language": "Go", "default_branch": "main"}
+        ]})))
+        .with_priority(1)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/forgejo/runner/commits"))
+        .and(query_param("sha", "main"))
+        .and(query_param("stat", "false"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header(
+                    "link",
+                    format!(r#"<{api_url}/repos/forgejo/runner/commits?sha=main&page=2>; rel="next""#)
+                        .as_str(),
+                )
+                .set_body_json(json!([
+                    {"commit": {"author": {"name": "Earl", "email": "earl@forgejo.org"}}, "author": {"login": "earl"}},
+                    {"commit": {"author": {"name": "Earl W.", "email": "earl@example.org"}}, "author": {"login": "earl"}},
+                    {"commit": {"author": {"name": "Gusted", "email": "gusted@forgejo.org"}}, "author": null}
+                ])),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/forgejo/runner/commits"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {"commit": {"author": {"name": "Earl", "email": "earl@forgejo.org"}}, "author": {"login": "earl"}}
+        ])))
+        .with_priority(1)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/forgejo/act/commits"))
+        .respond_with(
+            ResponseTemplate::new(409)
+                .set_body_json(json!({"message": "Git Repository is empty."})),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let cli = Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env_remove("GITHUB_ACCESS_TOKEN")
+        .env("GITEA_ACCESS_TOKEN", "GITEA_PAT")
+        .args(["--provider", "forgejo", "--gitea-url", &api_url])
+        .args(["-l", "go", "-p", "2"])
+        .assert();
+
+    cli.success().stdout(
+        predicate::str::is_match(
+            r"\[!\] project: forgejo/runner\s+stars:\s+80\s+bus factor:\s+1\s+key contributors: earl\s+user: earl\s+commits:\s+3\s+percentage: 75",
+        )
+        .unwrap()
+        .and(predicate::str::contains(
+            "project: forgejo/act",
+        ))
+        .and(predicate::str::contains("no contributor data: empty repository"))
+        .and(predicate::str::contains("forgejo/docs").not()),
+    );
+}
+#[tokio::test]
+async fn test_gitea_commit_limit() {
+    let mock_server = MockServer::start().await;
+    let api_url = format!("{}/api/v1", &mock_server.uri());
+
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/forgejo/runner"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(
+            {"full_name": "forgejo/runner", "stars_count": 80, "language": "Go", "default_branch": "main"}
+        )))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/forgejo/runner/commits"))
+        .and(query_param("limit", "50"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header(
+                    "link",
+                    format!(r#"<{api_url}/repos/forgejo/runner/commits?sha=main&page=2>; rel="next""#)
+                        .as_str(),
+                )
+                .set_body_json(json!([
+                    {"commit": {"author": {"name": "Earl", "email": "earl@forgejo.org"}}, "author": {"login": "earl"}}
+                ])),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/forgejo/runner/commits"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(500))
+        .with_priority(1)
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let cli = Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env_remove("GITHUB_ACCESS_TOKEN")
+        .env("GITEA_ACCESS_TOKEN", "GITEA_PAT")
+        .args(["--provider", "gitea", "--gitea-url", &api_url])
+        .args(["--repos", "forgejo/runner", "--max-commits", "50"])
+        .assert();
+
+    cli.success().stdout(
+        predicate::str::is_match(
+            r"project: forgejo/runner\s+stars:\s+80\s+bus factor:\s+1\s+key contributors: earl\s.*\(contributor list truncated\)",
+        )
+        .unwrap(),
+    );
+}
+#[tokio::test]
+async fn test_graphql_api() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/search/repositories"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(body_string_contains("search(query"))
+        .and(body_string_contains(r#""first":25"#))
+        .respond_with(ResponseTemplate::new(502))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(header("Authorization", "token PAT_TOKEN"))
+        .and(body_string_contains("search(query"))
+        .and(body_string_contains(r#""first":12"#))
+        .and(body_string_contains("language:rust sort:stars-desc"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": {
+            "rateLimit": {"cost": 1, "remaining": 4999},
+            "search": {
+                "repositoryCount": 2,
+                "pageInfo": {"hasNextPage": false, "endCursor": "s1"},
+                "nodes": [
+                    {
+                        "nameWithOwner": "rebels/falcon",
+                        "stargazerCount": 120,
+                        "primaryLanguage": {"name": "Rust"},
+                        "defaultBranchRef": {"name": "main", "target": {"history": {
+                            "pageInfo": {"hasNextPage": true, "endCursor": "c1"},
+                            "nodes": [
+                                {"author": {"name": "Luke", "email": "luke@rebels.org", "user": {"login": "luke"}}},
+                                {"author": {"name": "Leia", "email": "leia@rebels.org", "user": {"login": "leia"}}},
+                                {"author": {"name": "Han", "email": "han@smugglers.org", "user": null}}
+                            ]
+                        }}}
+                    },
+                    {
+                        "nameWithOwner": "empire/star",
+                        "stargazerCount": 80,
+                        "primaryLanguage": {"name": "Rust"},
+                        "defaultBranchRef": null
+                    }
+                ]
+            }
+        }})))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    //Secondary rate limit first, then the primary one, both retried after the advertised delay
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(body_string_contains(r#""historyCursor":"c1""#))
+        .respond_with(ResponseTemplate::new(403).insert_header("retry-after", "1"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(body_string_contains(r#""historyCursor":"c1""#))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-ratelimit-reset", now.to_string().as_str())
+                .set_body_json(json!({
+                    "data": null,
+                    "errors": [{"type": "RATE_LIMITED", "message": "API rate limit exceeded"}]
+                })),
+        )
+        .up_to_n_times(1)
+        .with_priority(2)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(body_string_contains("repository(owner"))
+        .and(body_string_contains(r#""historyCursor":"c1""#))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": {
+            "rateLimit": {"cost": 1, "remaining": 4998},
+            "repository": {
+                "nameWithOwner": "rebels/falcon",
+                "stargazerCount": 120,
+                "defaultBranchRef": {"name": "main", "target": {"history": {
+                    "pageInfo": {"hasNextPage": false, "endCursor": "c2"},
+                    "nodes": [
+                        {"author": {"name": "Luke", "email": "luke@rebels.org", "user": {"login": "luke"}}},
+                        {"author": {"name": "Luke", "email": "luke@rebels.org", "user": {"login": "luke"}}}
+                    ]
+                }}}
+            }
+        }})))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let url = format!("{}/search/repositories", &mock_server.uri());
+
+    let cli = Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+        .args(["--api", "graphql"])
+        .args(["-l", "rust", "-p", "2"])
+        .args(["-g", &url])
+        .assert();
+
+    cli.success().stdout(
+        predicate::str::is_match(
+            r"\[!\] project: rebels/falcon\s+stars:\s+120\s+bus factor:\s+1\s+key contributors: luke\s+user: luke\s+commits:\s+3\s+percentage: 75",
+        )
+        .unwrap()
+        .and(predicate::str::contains(
+            "project: empire/star",
+        ))
+        .and(predicate::str::contains("no contributor data: empty repository"))
+        .and(predicate::str::contains("Han").not()),
+    );
+}
+#[tokio::test]
+async fn test_graphql_matches_rest() {
+    let mock_server = MockServer::start().await;
+    let commit = |login: &str| json!({"author": {"name": login, "email": format!("{login}@rebels.org"), "user": {"login": login}}});
+    let history = |nodes: Vec<serde_json::Value>, next: bool| {
+        json!({"data": {
+            "rateLimit": {"cost": 1, "remaining": 4999},
+            "repository": {
+                "nameWithOwner": "rebels/falcon",
+                "stargazerCount": 120,
+                "primaryLanguage": {"name": "Rust"},
+                "defaultBranchRef": {"name": "main", "target": {"history": {
+                    "pageInfo": {"hasNextPage": next, "endCursor": "c1"},
+                    "nodes": nodes
+                }}}
+            }
+        }})
+    };
+
+    Mock::given(method("GET"))
+        .and(path("/repos/rebels/falcon"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "stargazers_count": 120,
+            "contributors_url": format!("{}/repos/rebels/falcon/contributors", &mock_server.uri()),
+            "full_name": "rebels/falcon",
+            "language": "Rust"
+        })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/rebels/falcon/contributors"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {"login": "luke", "contributions": 5},
+            {"login": "leia", "contributions": 3},
+            {"login": "han", "contributions": 2}
+        ])))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(body_string_contains(r#""historyCursor":null"#))
+        .respond_with(ResponseTemplate::new(200).set_body_json(history(
+            vec![
+                commit("luke"),
+                commit("luke"),
+                commit("leia"),
+                commit("luke"),
+                commit("leia"),
+            ],
+            true,
+        )))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(body_string_contains(r#""historyCursor":"c1""#))
+        .respond_with(ResponseTemplate::new(200).set_body_json(history(
+            vec![
+                commit("han"),
+                commit("luke"),
+                commit("leia"),
+                commit("luke"),
+                commit("han"),
+            ],
+            false,
+        )))
+        .mount(&mock_server)
+        .await;
+
+    let url = format!("{}/search/repositories", &mock_server.uri());
+    let analyse = |args: &[&str]| {
+        let output = Command::cargo_bin("bus_factor")
+            .unwrap()
+            .env("GITHUB_ACCESS_TOKEN", "PAT_TOKEN")
+            .args(["--repos", "rebels/falcon", "-t", "50", "-g", &url])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let rest = analyse(&[]);
+    assert!(predicate::str::is_match(
+        r"\[!\] project: rebels/falcon\s+stars:\s+120\s+bus factor:\s+1\s+key contributors: luke\s+user: luke\s+commits:\s+5\s+percentage: 50"
+    )
+    .unwrap()
+    .eval(&rest));
+    assert_eq!(analyse(&["--api", "graphql"]), rest);
+    //A single history page does not cover the whole history
+    assert!(analyse(&["--api", "graphql", "--max-commits", "5"])
+        .contains("(contributor list truncated)"));
+}
+#[tokio::test]
+async fn test_gitea_search_limits() {
+    let mock_server = MockServer::start().await;
+    let api_url = format!("{}/api/v1", &mock_server.uri());
+
+    //Endless search without a single Go repository
+    Mock::given(method("GET"))
+        .and(path("/api/v1/repos/search"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header(
+                    "link",
+                    format!(r#"<{api_url}/repos/search?sort=stars&order=desc&page=2>; rel="next""#)
+                        .as_str(),
+                )
+                .set_body_json(json!({"ok": true, "data": [
+                    {"full_name": "forgejo/docs", "stars_count": 70, "language": "Markdown", "default_branch": "main"}
+                ]})),
+        )
+        .expect(3)
+        .mount(&mock_server)
+        .await;
+
+    Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITEA_ACCESS_TOKEN", "GITEA_PAT")
+        .args(["--provider", "gitea", "--gitea-url", &api_url])
+        .args(["-l", "go", "-p", "2", "--max-search-pages", "3"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("project:").not());
+
+    Command::cargo_bin("bus_factor")
+        .unwrap()
+        .env("GITEA_ACCESS_TOKEN", "GITEA_PAT")
+        .args(["--provider", "gitea", "--gitea-url", &api_url])
+        .args(["--topic", "cli", "--query", "runner"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--topic can not be combined with --query",
+        ));
+}
@@ -0,0 +1,193 @@
+use anyhow::Result;
+use bus_factor::{
+    analyse_with, initialize_with, BusFactorQueryCommand, ContributorDetails, ContributorList,
+    DetectionStrategy, ForgeProvider, HttpClientDetails, RepositoryQueryResult,
+};
+use reqwest::header::HeaderMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::Sender;
+
+///In-memory forge recording every repository whose contributors were requested
+#[derive(Default)]
+struct FixedForge {
+    analysed: Arc<Mutex<Vec<String>>>,
+}
+
+fn repository(name: &str, client_details: Arc<HttpClientDetails>) -> RepositoryQueryResult {
+    RepositoryQueryResult {
+        stargazers: 42,
+        contributor_url: format!("memory://{name}/contributors"),
+        repository_url: format!("memory://{name}"),
+        default_branch: "main".to_string(),
+        project_name: name.to_string(),
+        language: client_details.command().search_languages()[0].clone(),
+        dependencies: vec![],
+        client_details,
+    }
+}
+
+fn contributor(login: &str, kind: &str, contributions: u64) -> ContributorDetails {
+    ContributorDetails {
+        login: Some(login.to_string()),
+        name: None,
+        email: None,
+        kind: kind.to_string(),
+        contributions,
+        other: Default::default(),
+    }
+}
+
+impl ForgeProvider for FixedForge {
+    fn name(&self) -> &str {
+        "memory"
+    }
+    fn authenticate(&self, _command: &BusFactorQueryCommand) -> Result<HeaderMap> {
+        Ok(HeaderMap::new())
+    }
+    async fn repository(
+        &self,
+        client_details: Arc<HttpClientDetails>,
+        name: &str,
+    ) -> Result<Option<RepositoryQueryResult>> {
+        Ok((name != "core/missing").then(|| repository(name, client_details)))
+    }
+    async fn discover(
+        &self,
+        client_details: Arc<HttpClientDetails>,
+        tx: Sender<RepositoryQueryResult>,
+    ) -> Result<()> {
+        for name in ["core/engine", "core/tools"] {
+            tx.send(repository(name, client_details.clone())).await?;
+        }
+        Ok(())
+    }
+    async fn contributors(&self, data: &RepositoryQueryResult) -> Result<ContributorList> {
+        self.analysed
+            .lock()
+            .unwrap()
+            .push(data.project_name.clone());
+        Ok(ContributorList {
+            contributors: vec![
+                contributor("luke", "User", 90),
+                contributor("r2d2", "Bot", 500),
+                contributor("leia", "User", 10),
+            ],
+            truncated: false,
+        })
+    }
+}
+
+fn analysed(command: BusFactorQueryCommand) -> Vec<String> {
+    let forge = FixedForge::default();
+    let analysed = forge.analysed.clone();
+    initialize_with(command, forge).unwrap();
+    let mut analysed = analysed.lock().unwrap().clone();
+    analysed.sort();
+    analysed
+}
+
+#[test]
+fn test_custom_forge_discovery() {
+    assert_eq!(
+        analysed(BusFactorQueryCommand {
+            languages: vec!["rust".to_string()],
+            ..Default::default()
+        }),
+        vec!["core/engine", "core/tools"]
+    );
+}
+
+#[test]
+fn test_custom_forge_explicit_repositories() {
+    assert_eq!(
+        analysed(BusFactorQueryCommand {
+            repositories: vec![
+                "core/engine".to_string(),
+                "core/missing".to_string(),
+                "core/engine".to_string(),
+            ],
+            ..Default::default()
+        }),
+        vec!["core/engine"]
+    );
+}
+
+#[test]
+fn test_custom_forge_results() {
+    let results = analyse_with(
+        BusFactorQueryCommand {
+            repositories: vec!["core/engine".to_string()],
+            ..Default::default()
+        },
+        FixedForge::default(),
+    )
+    .unwrap();
+    assert_eq!(results.len(), 1);
+    let result = &results[0];
+    assert_eq!(result.repo_name, "core/engine");
+    assert_eq!(result.stargazers, 42);
+    assert_eq!(result.no_data, None);
+    //r2d2 is a bot and dropped before calculation
+    assert_eq!(result.bus_factor, 1);
+    assert_eq!(result.key_contributors, vec!["luke"]);
+    let dominant: Vec<_> = result
+        .dominant_contributors
+        .iter()
+        .map(|dominant| {
+            (
+                dominant.login.as_str(),
+                dominant.contributions,
+                dominant.percentage,
+            )
+        })
+        .collect();
+    assert_eq!(dominant, vec![("luke", 90, 90)]);
+    assert!(result.flagged);
+}
+
+#[test]
+fn test_custom_forge_rejects_local_repositories() {
+    let err = analyse_with(
+        BusFactorQueryCommand {
+            repositories: vec!["core/engine".to_string()],
+            local_repositories: vec![".".into()],
+            ..Default::default()
+        },
+        FixedForge::default(),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("Local repositories"));
+}
+
+#[test]
+fn test_custom_forge_rejects_unsupported_strategy() {
+    let err = initialize_with(
+        BusFactorQueryCommand {
+            repositories: vec!["core/engine".to_string()],
+            strategy: DetectionStrategy::TruckFactor,
+            ..Default::default()
+        },
+        FixedForge::default(),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("truck-factor"));
+}
+
+#[test]
+fn test_custom_forge_rejects_zero_page_limit() {
+    let err = initialize_with(
+        BusFactorQueryCommand {
+            repositories: vec!["core/engine".to_string()],
+            max_contributor_pages: 0,
+            ..Default::default()
+        },
+        FixedForge::default(),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("max_contributor_pages"));
+}
+
+#[test]
+fn test_custom_forge_nothing_to_analyse() {
+    assert!(analysed(BusFactorQueryCommand::default()).is_empty());
+}